@@ -64,8 +64,8 @@ async fn start_tunnel() -> anyhow::Result<impl UrlTunnel> {
         // .request_header("X-Req-Yup", "true")
         // .response_header("X-Res-Yup", "true")
         // .scheme(ngrok::Scheme::HTTPS)
-        // .websocket_tcp_conversion()
-        // .webhook_verification("twilio", "asdf"),
+        // .websocket_tcp_converter()
+        // .verify_webhook(WebhookProvider::Twilio, "asdf"),
         .metadata("example tunnel metadata from rust")
         .listen()
         .await?;
@@ -5,7 +5,10 @@ use axum::{
     routing::get,
     Router,
 };
-use ngrok::prelude::*;
+use ngrok::{
+    config::TlsTermination,
+    prelude::*,
+};
 
 const CERT: &[u8] = include_bytes!("domain.crt");
 const KEY: &[u8] = include_bytes!("domain.key");
@@ -48,7 +51,10 @@ async fn start_tunnel() -> anyhow::Result<impl Tunnel> {
         // .forwards_to("example rust"),
         // .mutual_tlsca(CA_CERT.into())
         // .proxy_proto(ProxyProto::None)
-        .termination(CERT.into(), KEY.into())
+        .tls_termination(TlsTermination::Edge {
+            cert_pem: CERT.into(),
+            key_pem: KEY.into(),
+        })
         .metadata("example tunnel metadata from rust")
         .listen()
         .await?;
@@ -1,7 +1,16 @@
 use std::{
     collections::HashMap,
     env,
+    fs,
+    io,
+    iter,
+    net::IpAddr,
+    path::{
+        Path,
+        PathBuf,
+    },
     process,
+    time::Duration,
 };
 
 use async_trait::async_trait;
@@ -9,6 +18,7 @@ use once_cell::sync::OnceCell;
 
 pub use crate::internals::proto::ProxyProto;
 use crate::{
+    config::traffic_policy::PolicySource,
     internals::proto::{
         BindExtra,
         BindOpts,
@@ -46,17 +56,51 @@ pub trait TunnelBuilder: From<Session> {
     type Tunnel: Tunnel;
 
     /// Begin listening for new connections on this tunnel.
+    ///
+    /// If the ngrok service refuses to start the tunnel, the reason is
+    /// available via [RpcError::bind_error].
     async fn listen(&self) -> Result<Self::Tunnel, RpcError>;
+
+    /// Check the tunnel's configuration for problems without starting it.
+    ///
+    /// This performs the same checks as [TunnelBuilder::listen] does before
+    /// contacting the ngrok service, returning
+    /// [RpcError::InvalidConfig] if any fail. The ngrok service has no way
+    /// to validate a tunnel without binding it, so problems it would report,
+    /// such as an unreserved domain, are only detected by `listen`.
+    ///
+    /// The default implementation performs no checks.
+    fn validate(&self) -> Result<(), RpcError> {
+        Ok(())
+    }
 }
 
 macro_rules! impl_builder {
-    ($(#[$m:meta])* $name:ident, $opts:ty, $tun:ident) => {
+    ($(#[$m:meta])* $name:ident, $opts:ty, $tun:ident $(, $extra:tt)*) => {
         $(#[$m])*
         #[derive(Clone)]
         pub struct $name {
             options: $opts,
             // Note: This is only optional for testing purposes.
             session: Option<Session>,
+            bind_retries: usize,
+            rebind: crate::session::RebindOpts,
+            url_file: Option<std::path::PathBuf>,
+        }
+
+        #[cfg(test)]
+        impl $name {
+            // A builder that isn't attached to a session, for testing how its
+            // options are set.
+            pub(crate) fn without_session() -> Self {
+                $name {
+                    options: Default::default(),
+                    session: None,
+                    bind_retries: 0,
+                    rebind: Default::default(),
+                    url_file: None,
+                }
+            }
         }
 
         impl From<Session> for $name {
@@ -64,27 +108,154 @@ macro_rules! impl_builder {
                 $name {
                     options: Default::default(),
                     session: session.into(),
+                    bind_retries: 0,
+                    rebind: Default::default(),
+                    url_file: None,
                 }
             }
         }
 
+        impl $name {
+            /// Retry starting the tunnel up to the given number of times when
+            /// [TunnelBuilder::listen] fails with a transient error, as
+            /// determined by [RpcError::is_retryable].
+            ///
+            /// Retries back off exponentially, starting at 500ms and capped at
+            /// 30 seconds. Errors that won't resolve on their own, such as
+            /// rejected credentials, are returned immediately. Defaults to 0.
+            pub fn bind_retries(&mut self, retries: usize) -> &mut Self {
+                self.bind_retries = retries;
+                self
+            }
+
+            /// Keep trying to rebind the tunnel according to the given policy
+            /// if the ngrok service refuses to restore it when the session
+            /// reconnects.
+            ///
+            /// Without a policy, such a failure fails the whole reconnect
+            /// attempt, which holds up the session's other tunnels until it's
+            /// retried successfully. With one, the session reconnects without
+            /// this tunnel, which is rebound in the background. If its
+            /// configuration is no longer accepted, or the policy runs out of
+            /// retries, the error is returned from the tunnel as
+            /// [AcceptError::Rebind](crate::tunnel::AcceptError::Rebind).
+            pub fn reconnect_policy(
+                &mut self,
+                policy: crate::session::TunnelReconnectPolicy,
+            ) -> &mut Self {
+                self.rebind.policy = Some(policy);
+                self
+            }
+
+            /// Call the provided function with the outcome of each attempt to
+            /// rebind the tunnel in the background, as configured by
+            /// [reconnect_policy](Self::reconnect_policy).
+            pub fn on_rebind(
+                &mut self,
+                callback: impl Fn(Result<(), &RpcError>) + Send + Sync + 'static,
+            ) -> &mut Self {
+                self.rebind.on_rebind = Some(std::sync::Arc::new(callback));
+                self
+            }
+
+            /// Write the tunnel's URL to the file at the given path once it
+            /// starts, so that other tools can discover it.
+            ///
+            /// The file is replaced atomically, so readers never see it
+            /// partially written, and it's removed when the tunnel is closed
+            /// or dropped. If it can't be written, the tunnel is closed and
+            /// [TunnelBuilder::listen] fails with [RpcError::UrlFile].
+            /// Labeled tunnels don't have a URL, so their file is left empty.
+            pub fn write_url_file(&mut self, path: impl Into<std::path::PathBuf>) -> &mut Self {
+                self.url_file = Some(path.into());
+                self
+            }
+        }
+
         #[async_trait]
         impl TunnelBuilder for $name {
             type Tunnel = $tun;
 
             async fn listen(&self) -> Result<$tun, RpcError> {
-                Ok($tun {
-                    inner: self
-                        .session
-                        .as_ref()
-                        .unwrap()
-                        .start_tunnel(&self.options)
-                        .await?,
-                })
+                let session = self.session.as_ref().unwrap();
+                let mut inner = tokio_retry::RetryIf::start(
+                    crate::config::common::bind_retry_delays(self.bind_retries),
+                    || session.start_tunnel(&self.options, self.rebind.clone()),
+                    |error: &RpcError| {
+                        let retry = error.is_retryable();
+                        if retry {
+                            tracing::warn!(%error, "failed to start tunnel, retrying");
+                        }
+                        retry
+                    },
+                )
+                .await?;
+                if let Some(path) = &self.url_file {
+                    crate::config::common::write_url_file(path, &inner.url)
+                        .map_err(RpcError::UrlFile)?;
+                    inner.url_file = Some(path.clone());
+                }
+                Ok($tun { inner })
+            }
+
+            fn validate(&self) -> Result<(), RpcError> {
+                TunnelConfig::validate(&self.options).map_err(RpcError::InvalidConfig)
             }
         }
+
+        $(
+            impl_builder!($extra; $name);
+        )*
     };
+    (endpoint; $name:ident) => {
+        impl $name {
+            /// Sets whether this tunnel's endpoint may be pooled with those of
+            /// other agents binding the same URL. Defaults to `false`.
+            ///
+            /// When every tunnel for a URL has pooling enabled, each one that
+            /// binds it joins the endpoint's pool instead of failing because
+            /// the URL is already in use, and the ngrok edge spreads new
+            /// connections across the pool. A tunnel that closes, or whose
+            /// session disconnects, stops receiving connections while the rest
+            /// of the pool carries on, which allows running redundant agents
+            /// without a separate load balancer.
+            pub fn pooling_enabled(&mut self, pooling_enabled: bool) -> &mut Self {
+                self.options.common_opts.pooling_enabled = pooling_enabled;
+                self
+            }
+        }
+    };
+}
+// The delays between attempts to start a tunnel, for up to `retries` retries.
+pub(crate) fn bind_retry_delays(retries: usize) -> impl Iterator<Item = Duration> {
+    iter::successors(Some(BIND_RETRY_BASE), |delay| {
+        Some(delay.saturating_mul(2).min(BIND_RETRY_MAX))
+    })
+    .take(retries)
+}
+
+// Write the url to the file at the given path, via a temporary file alongside
+// it so that the file is replaced in a single rename.
+pub(crate) fn write_url_file(path: &Path, url: &str) -> io::Result<()> {
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "url file path has no file name",
+        )
+    })?;
+    let mut tmp_name = name.to_os_string();
+    tmp_name.push(format!(".{}.tmp", process::id()));
+    let tmp: PathBuf = path.with_file_name(tmp_name);
+    if let Err(error) = fs::write(&tmp, url).and_then(|_| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(error);
+    }
+    Ok(())
 }
+
+const BIND_RETRY_BASE: Duration = Duration::from_millis(500);
+const BIND_RETRY_MAX: Duration = Duration::from_secs(30);
+
 /// Tunnel configuration trait, implemented by our top-level config objects.
 ///
 /// "Sealed," i.e. not implementable outside of the crate.
@@ -101,6 +272,11 @@ pub(crate) trait TunnelConfig {
     fn opts(&self) -> Option<BindOpts>;
     /// The labels for this tunnel.
     fn labels(&self) -> HashMap<String, String>;
+    /// Check for invalid options before binding the tunnel, returning a
+    /// description of the problem.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 // delegate references
@@ -123,6 +299,9 @@ where
     fn labels(&self) -> HashMap<String, String> {
         (**self).labels()
     }
+    fn validate(&self) -> Result<(), String> {
+        (**self).validate()
+    }
 }
 
 /// Restrictions placed on the origin of incoming connections to the edge.
@@ -141,6 +320,51 @@ impl CidrRestrictions {
     pub(crate) fn deny(&mut self, cidr: impl Into<String>) {
         self.denied.push(cidr.into());
     }
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self
+            .allowed
+            .iter()
+            .chain(&self.denied)
+            .find(|cidr| !is_valid_cidr(cidr))
+        {
+            Some(cidr) => Err(format!("invalid CIDR: {cidr:?}")),
+            None => Ok(()),
+        }
+    }
+}
+
+// Check that the string is an IPv4 or IPv6 CIDR, e.g. "10.0.0.0/8".
+fn is_valid_cidr(cidr: &str) -> bool {
+    let (addr, prefix) = match cidr.split_once('/') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let max_prefix = match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    // Reject signs and redundant zeros, which u8 parsing allows.
+    let canonical_prefix = prefix
+        .parse::<u8>()
+        .ok()
+        .filter(|p| p.to_string() == prefix);
+    matches!(canonical_prefix, Some(p) if p <= max_prefix)
+}
+
+// Check that the string looks like a domain name, e.g. "example.com".
+pub(crate) fn is_valid_domain(domain: &str) -> bool {
+    let labels = domain.split('.').collect::<Vec<_>>();
+    domain.len() <= 253 && labels.len() > 1 && labels.iter().all(|label| is_valid_label(label))
+}
+
+// Check that a string is a single valid label of a hostname.
+pub(crate) fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
 }
 
 // Common
@@ -156,9 +380,32 @@ pub(crate) struct CommonOpts {
     // Tunnel backend metadata. Viewable via the dashboard and API, but has no
     // bearing on tunnel behavior.
     pub(crate) forwards_to: Option<String>,
+    // The traffic policy to apply at the edge.
+    pub(crate) traffic_policy: Option<PolicySource>,
+    // Whether the endpoint may be shared with other agents binding the same
+    // URL.
+    pub(crate) pooling_enabled: bool,
 }
 
 impl CommonOpts {
+    // Check the options shared by all tunnel types.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        self.cidr_restrictions.validate()?;
+        if let Some(policy) = &self.traffic_policy {
+            policy.validate()?;
+        }
+        self.traffic_policy()?;
+        Ok(())
+    }
+
+    // Get the wire format of the traffic policy, empty if there isn't one.
+    pub(crate) fn traffic_policy(&self) -> Result<String, String> {
+        match &self.traffic_policy {
+            Some(policy) => Ok(policy.to_value()?.to_string()),
+            None => Ok(String::new()),
+        }
+    }
+
     // Get the proto version of cidr restrictions
     pub(crate) fn ip_restriction(&self) -> Option<IpRestriction> {
         (!self.cidr_restrictions.allowed.is_empty() || !self.cidr_restrictions.denied.is_empty())
@@ -185,3 +432,93 @@ impl From<&[bytes::Bytes]> for MutualTls {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bind_retry_delays() {
+        assert_eq!(0, bind_retry_delays(0).count());
+        let delays = bind_retry_delays(10).collect::<Vec<_>>();
+        assert_eq!(10, delays.len());
+        assert_eq!(Duration::from_millis(500), delays[0]);
+        assert_eq!(Duration::from_secs(1), delays[1]);
+        assert_eq!(Duration::from_secs(30), delays[9]);
+    }
+
+    #[test]
+    fn test_write_url_file() {
+        let dir = env::temp_dir().join(format!("ngrok-test-url-file-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("url");
+
+        write_url_file(&path, "https://one.ngrok.app").unwrap();
+        write_url_file(&path, "https://two.ngrok.app").unwrap();
+        assert_eq!("https://two.ngrok.app", fs::read_to_string(&path).unwrap());
+        // Only the url file itself is left behind.
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+
+        assert!(write_url_file(&dir.join("missing/url"), "https://ngrok.app").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cidr_validation() {
+        for cidr in [
+            "0.0.0.0/0",
+            "10.1.1.1/32",
+            "::/0",
+            "2001:db8::/32",
+            "::1/128",
+        ] {
+            assert!(is_valid_cidr(cidr), "{cidr}");
+        }
+        for cidr in [
+            "",
+            "10.1.1.1",
+            "10.1.1.1/33",
+            "::1/129",
+            "10.1.1/8",
+            "10.1.1.1/+8",
+            "10.1.1.1/08",
+            "localhost/8",
+        ] {
+            assert!(!is_valid_cidr(cidr), "{cidr}");
+        }
+
+        let mut restrictions = CidrRestrictions::default();
+        restrictions.allow("10.0.0.0/8");
+        restrictions.deny("10.1.1.1/32");
+        assert!(restrictions.validate().is_ok());
+        restrictions.deny("nope");
+        assert_eq!(
+            Err("invalid CIDR: \"nope\"".to_string()),
+            restrictions.validate()
+        );
+    }
+
+    #[test]
+    fn test_domain_validation() {
+        for domain in [
+            "example.com",
+            "sub.example.co.uk",
+            "my-company.io",
+            "xn--bcher-kva.example",
+        ] {
+            assert!(is_valid_domain(domain), "{domain}");
+        }
+        for domain in [
+            "",
+            "localhost",
+            "example..com",
+            ".example.com",
+            "-example.com",
+            "example-.com",
+            "exa mple.com",
+            "user@example.com",
+        ] {
+            assert!(!is_valid_domain(domain), "{domain}");
+        }
+    }
+}
@@ -2,6 +2,14 @@ use std::collections::HashMap;
 
 use crate::internals::proto::Headers as HeaderProto;
 
+/// Headers the ngrok edge adds to requests from users authenticated via OAuth
+/// or OIDC.
+pub(crate) const IDENTITY_HEADERS: &[&str] = &[
+    "ngrok-auth-user-email",
+    "ngrok-auth-user-id",
+    "ngrok-auth-user-name",
+];
+
 /// HTTP Headers to modify at the ngrok edge.
 #[derive(Clone, Default)]
 pub(crate) struct Headers {
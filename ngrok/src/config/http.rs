@@ -2,6 +2,7 @@ use std::{
     borrow::Borrow,
     collections::HashMap,
     str::FromStr,
+    time::Duration,
 };
 
 use async_trait::async_trait;
@@ -19,13 +20,27 @@ use crate::{
     config::{
         common::{
             default_forwards_to,
+            is_valid_domain,
+            is_valid_label,
             CommonOpts,
             TunnelConfig,
         },
-        headers::Headers,
+        headers::{
+            Headers,
+            IDENTITY_HEADERS,
+        },
         oauth::OauthOptions,
         oidc::OidcOptions,
-        webhook_verification::WebhookVerification,
+        traffic_policy::{
+            Action,
+            PolicySource,
+            Rule,
+            TrafficPolicy,
+        },
+        webhook_verification::{
+            WebhookProvider,
+            WebhookVerification,
+        },
     },
     internals::proto::{
         BasicAuth,
@@ -49,8 +64,9 @@ pub struct InvalidSchemeString(String);
 
 /// The URL scheme for this HTTP endpoint.
 ///
-/// [Scheme::HTTPS] will enable TLS termination at the ngrok edge.
-#[derive(Clone, Default, Eq, PartialEq)]
+/// [Scheme::HTTPS] will enable TLS termination at the ngrok edge, and is
+/// required by options that depend on it, such as mutual TLS.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
 pub enum Scheme {
     /// The `http` URL scheme.
     HTTP,
@@ -71,15 +87,75 @@ impl FromStr for Scheme {
     }
 }
 
+/// The key by which requests are grouped for rate limiting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// Limit requests from each client IP address separately.
+    Ip,
+    /// Limit requests separately for each value of the named request header,
+    /// e.g. an API key.
+    Header(String),
+}
+
+// A rate limit enforced at the ngrok edge via a traffic policy rule.
+#[derive(Debug, Clone)]
+struct RateLimit {
+    requests: u32,
+    per: Duration,
+    by: RateLimitKey,
+}
+
+impl RateLimit {
+    fn validate(&self) -> Result<(), String> {
+        if self.requests == 0 {
+            return Err("rate limit must allow at least one request".into());
+        }
+        if self.per.as_millis() == 0 {
+            return Err("rate limit period must be at least 1ms".into());
+        }
+        Ok(())
+    }
+
+    // The traffic policy rule enforcing this limit.
+    fn to_rule(&self) -> Rule {
+        let bucket_key = match &self.by {
+            RateLimitKey::Ip => "conn.client_ip".to_string(),
+            RateLimitKey::Header(name) => format!("req.headers['{}']", name.to_lowercase()),
+        };
+        Rule::new("rate limit")
+            .action(Action::new("rate-limit").config(serde_json::json!({
+                "name": "rate limit",
+                "algorithm": "sliding_window",
+                "capacity": self.requests,
+                "rate": format!("{}ms", self.per.as_millis()),
+                "bucket_key": [bucket_key],
+            })))
+            .to_owned()
+    }
+}
+
+// The traffic policy rule redirecting plain http requests to https.
+fn https_redirect_rule() -> Rule {
+    Rule::new("https redirect")
+        .expression("req.url.scheme == 'http'")
+        .action(Action::new("redirect").config(serde_json::json!({
+            "from": "^http://(.*)$",
+            "to": "https://$1",
+            "status_code": 301,
+        })))
+        .to_owned()
+}
+
 /// The options for a HTTP edge.
 #[derive(Default, Clone)]
 struct HttpOptions {
     pub(crate) common_opts: CommonOpts,
     pub(crate) scheme: Scheme,
     pub(crate) domain: Option<String>,
+    pub(crate) subdomain: Option<String>,
     pub(crate) mutual_tlsca: Vec<bytes::Bytes>,
     pub(crate) compression: bool,
-    pub(crate) websocket_tcp_conversion: bool,
+    pub(crate) websocket_tcp_converter: bool,
     pub(crate) circuit_breaker: f64,
     pub(crate) request_headers: Headers,
     pub(crate) response_headers: Headers,
@@ -87,6 +163,34 @@ struct HttpOptions {
     pub(crate) oauth: Option<OauthOptions>,
     pub(crate) oidc: Option<OidcOptions>,
     pub(crate) webhook_verification: Option<WebhookVerification>,
+    pub(crate) rate_limit: Option<RateLimit>,
+    pub(crate) https_redirect: bool,
+}
+
+impl HttpOptions {
+    // The traffic policy to send, including the rules for any options that
+    // are implemented via policy.
+    fn traffic_policy(&self) -> Result<String, String> {
+        let rules = self
+            .https_redirect
+            .then(https_redirect_rule)
+            .into_iter()
+            .chain(self.rate_limit.as_ref().map(RateLimit::to_rule))
+            .map(|rule| serde_json::to_value(rule).expect("rules are always serializable"))
+            .collect::<Vec<_>>();
+        if rules.is_empty() {
+            return self.common_opts.traffic_policy();
+        }
+        let mut policy = match &self.common_opts.traffic_policy {
+            Some(policy) => policy.to_value()?,
+            None => serde_json::json!({}),
+        };
+        match policy["inbound"].as_array_mut() {
+            Some(inbound) => inbound.extend(rules),
+            None => policy["inbound"] = serde_json::Value::Array(rules),
+        }
+        Ok(policy.to_string())
+    }
 }
 
 impl TunnelConfig for HttpOptions {
@@ -101,6 +205,7 @@ impl TunnelConfig for HttpOptions {
             token: Default::default(),
             ip_policy_ref: Default::default(),
             metadata: self.common_opts.metadata.clone().unwrap_or_default(),
+            pooling_enabled: self.common_opts.pooling_enabled,
         }
     }
     fn proto(&self) -> String {
@@ -110,9 +215,18 @@ impl TunnelConfig for HttpOptions {
         "https".into()
     }
     fn opts(&self) -> Option<BindOpts> {
+        let mut request_headers = self.request_headers.clone();
+        let strip_identity_headers = self.oauth.iter().any(|o| o.strip_identity_headers)
+            || self.oidc.iter().any(|o| o.strip_identity_headers);
+        if strip_identity_headers {
+            for header in IDENTITY_HEADERS {
+                request_headers.remove(*header);
+            }
+        }
         let http_endpoint = HttpEndpoint {
             proxy_proto: self.common_opts.proxy_proto,
             hostname: self.domain.clone().unwrap_or_default(),
+            subdomain: self.subdomain.clone().unwrap_or_default(),
             compression: self.compression.then_some(Compression {}),
             circuit_breaker: (self.circuit_breaker != 0f64).then_some(CircuitBreaker {
                 error_threshold: self.circuit_breaker,
@@ -124,17 +238,19 @@ impl TunnelConfig for HttpOptions {
             webhook_verification: self.webhook_verification.clone().map(From::from),
             mutual_tls_ca: (!self.mutual_tlsca.is_empty())
                 .then_some(self.mutual_tlsca.as_slice().into()),
-            request_headers: self
-                .request_headers
+            request_headers: request_headers
                 .has_entries()
-                .then_some(self.request_headers.clone().into()),
+                .then(|| request_headers.into()),
             response_headers: self
                 .response_headers
                 .has_entries()
                 .then_some(self.response_headers.clone().into()),
             websocket_tcp_converter: self
-                .websocket_tcp_conversion
+                .websocket_tcp_converter
                 .then_some(WebsocketTcpConverter {}),
+            // A policy that fails to parse has already been rejected by
+            // validate.
+            traffic_policy: self.traffic_policy().unwrap_or_default(),
             ..Default::default()
         };
 
@@ -143,6 +259,61 @@ impl TunnelConfig for HttpOptions {
     fn labels(&self) -> HashMap<String, String> {
         HashMap::new()
     }
+    fn validate(&self) -> Result<(), String> {
+        self.common_opts.validate()?;
+        self.traffic_policy()?;
+        if let Some(oauth) = &self.oauth {
+            oauth.validate()?;
+        }
+        if let Some(oidc) = &self.oidc {
+            oidc.validate()?;
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.validate()?;
+        }
+        if let Some(domain) = &self.domain {
+            // Wildcard domains are reserved as e.g. "*.example.com".
+            if !is_valid_domain(domain.strip_prefix("*.").unwrap_or(domain)) {
+                return Err(format!("invalid domain: {domain:?}"));
+            }
+        }
+        if let Some(subdomain) = &self.subdomain {
+            if !is_valid_label(subdomain) {
+                return Err(format!("invalid subdomain: {subdomain:?}"));
+            }
+            if self.domain.is_some() {
+                return Err("a subdomain can't be combined with a domain".into());
+            }
+        }
+        if !(0f64..=1f64).contains(&self.circuit_breaker) {
+            return Err(format!(
+                "circuit breaker threshold must be between 0.0 and 1.0, got {}",
+                self.circuit_breaker
+            ));
+        }
+        for (username, password) in &self.basic_auth {
+            if username.is_empty() || username.contains(':') {
+                return Err(format!("invalid basic auth username: {username:?}"));
+            }
+            if !(8..=128).contains(&password.chars().count()) {
+                return Err(format!(
+                    "basic auth password for {username:?} must be between 8 and 128 characters"
+                ));
+            }
+        }
+        if self.scheme == Scheme::HTTP && !self.mutual_tlsca.is_empty() {
+            return Err("mutual tls requires the https scheme".into());
+        }
+        if self.scheme == Scheme::HTTPS && self.https_redirect {
+            return Err("https redirects require the http scheme".into());
+        }
+        if self.websocket_tcp_converter && self.https_redirect {
+            return Err(
+                "the websocket-to-tcp converter can't be combined with https redirects".into(),
+            );
+        }
+        Ok(())
+    }
 }
 
 // transform into the wire protocol format
@@ -167,16 +338,16 @@ impl From<(String, String)> for BasicAuthCredential {
 
 impl_builder! {
     /// A builder for a tunnel backing an HTTP endpoint.
-    HttpTunnelBuilder, HttpOptions, HttpTunnel
+    HttpTunnelBuilder, HttpOptions, HttpTunnel, endpoint
 }
 
 impl HttpTunnelBuilder {
-    /// Add the provided CIDR to the allowlist.
+    /// Add the provided CIDR to the allowlist. Invalid CIDRs fail at listen time.
     pub fn allow_cidr(&mut self, cidr: impl Into<String>) -> &mut Self {
         self.options.common_opts.cidr_restrictions.allow(cidr);
         self
     }
-    /// Add the provided CIDR to the denylist.
+    /// Add the provided CIDR to the denylist. Invalid CIDRs fail at listen time.
     pub fn deny_cidr(&mut self, cidr: impl Into<String>) -> &mut Self {
         self.options.common_opts.cidr_restrictions.deny(cidr);
         self
@@ -186,6 +357,20 @@ impl HttpTunnelBuilder {
         self.options.common_opts.proxy_proto = proxy_proto;
         self
     }
+    /// Sets the traffic policy to apply at the ngrok edge. Options such as
+    /// [HttpTunnelBuilder::rate_limit] add their rules to it.
+    pub fn traffic_policy(&mut self, policy: impl Borrow<TrafficPolicy>) -> &mut Self {
+        self.options.common_opts.traffic_policy =
+            Some(PolicySource::Typed(policy.borrow().to_owned()));
+        self
+    }
+
+    /// Sets the traffic policy from a JSON or YAML document, such as one copied
+    /// from the ngrok dashboard.
+    pub fn traffic_policy_str(&mut self, policy: impl Into<String>) -> &mut Self {
+        self.options.common_opts.traffic_policy = Some(PolicySource::Raw(policy.into()));
+        self
+    }
     /// Sets the opaque metadata string for this tunnel.
     pub fn metadata(&mut self, metadata: impl Into<String>) -> &mut Self {
         self.options.common_opts.metadata = Some(metadata.into());
@@ -197,16 +382,51 @@ impl HttpTunnelBuilder {
         self.options.common_opts.forwards_to = Some(forwards_to.into());
         self
     }
-    /// Sets the scheme for this edge.
+    /// Sets the scheme for this edge. Defaults to [Scheme::HTTPS].
+    ///
+    /// Each tunnel serves a single scheme, so an https-only endpoint is the
+    /// default, and [Scheme::HTTP] serves plain http only. To serve both,
+    /// start a tunnel for each, optionally with
+    /// [HttpTunnelBuilder::force_https_redirect] on the http one. Options
+    /// that rely on TLS termination at the edge, such as
+    /// [HttpTunnelBuilder::mutual_tlsca], require [Scheme::HTTPS].
+    /// Otherwise, starting the tunnel will fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
     pub fn scheme(&mut self, scheme: Scheme) -> &mut Self {
         self.options.scheme = scheme;
         self
     }
     /// Sets the domain to request for this edge.
+    ///
+    /// Without a domain, the ngrok service assigns a random subdomain, such as
+    /// `https://1234-abcd.ngrok-free.app`, which isn't guaranteed to stay the
+    /// same when the tunnel is started again. Use a domain reserved in the
+    /// [ngrok dashboard] to get a URL that's stable across restarts and
+    /// reconnects, and can be safely given out, e.g. for webhooks.
+    ///
+    /// The domain must be a valid hostname, optionally prefixed with `*.` for
+    /// wildcard domains, or starting the tunnel will fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    ///
+    /// [ngrok dashboard]: https://dashboard.ngrok.com/cloud-edge/domains
     pub fn domain(&mut self, domain: impl Into<String>) -> &mut Self {
         self.options.domain = Some(domain.into());
         self
     }
+    /// Sets the subdomain of the account's ngrok domain to request for this
+    /// edge, such as `myapp` for `https://myapp.ngrok.app`.
+    ///
+    /// The subdomain must be a single hostname label of letters, digits, and
+    /// hyphens, and can't be combined with [HttpTunnelBuilder::domain], or
+    /// starting the tunnel will fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    /// If another session already has it, starting the tunnel fails with an
+    /// error whose [RpcError::bind_error](crate::session::RpcError::bind_error)
+    /// is [BindError::DomainInUse](crate::session::BindError::DomainInUse).
+    pub fn subdomain(&mut self, subdomain: impl Into<String>) -> &mut Self {
+        self.options.subdomain = Some(subdomain.into());
+        self
+    }
     /// Adds a certificate in PEM format to use for mutual TLS authentication.
     ///
     /// These will be used to authenticate client certificates for requests at
@@ -215,18 +435,41 @@ impl HttpTunnelBuilder {
         self.options.mutual_tlsca.push(mutual_tlsca);
         self
     }
-    /// Enables gzip compression.
+    /// Enables gzip compression of responses at the ngrok edge.
+    /// Compression is disabled by default.
     pub fn compression(&mut self) -> &mut Self {
         self.options.compression = true;
         self
     }
     /// Enables the websocket-to-tcp converter.
-    pub fn websocket_tcp_conversion(&mut self) -> &mut Self {
-        self.options.websocket_tcp_conversion = true;
+    ///
+    /// Websocket connections to this edge are converted to raw TCP streams
+    /// before being sent through the tunnel, so the local service should speak
+    /// the tcp protocol being carried rather than HTTP. When forwarding via
+    /// [TunnelExt::forward](crate::prelude::TunnelExt::forward), this
+    /// typically means using a `tcp://` url.
+    ///
+    /// The converter is only available on http and https edges. It can't be
+    /// combined with [HttpTunnelBuilder::force_https_redirect], which would
+    /// redirect every websocket request before it could be converted, and
+    /// starting such a tunnel will fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    pub fn websocket_tcp_converter(&mut self) -> &mut Self {
+        self.options.websocket_tcp_converter = true;
         self
     }
+    /// Enables the websocket-to-tcp converter.
+    #[deprecated(note = "renamed to `websocket_tcp_converter`")]
+    pub fn websocket_tcp_conversion(&mut self) -> &mut Self {
+        self.websocket_tcp_converter()
+    }
     /// Sets the 5XX response ratio at which the ngrok edge will stop sending
     /// requests to this tunnel.
+    ///
+    /// The ratio must be between 0.0 and 1.0. Like the builder's other
+    /// settings, this isn't checked here but when the tunnel is started, where
+    /// an out of range ratio causes [TunnelBuilder::listen] to fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
     pub fn circuit_breaker(&mut self, circuit_breaker: f64) -> &mut Self {
         self.options.circuit_breaker = circuit_breaker;
         self
@@ -251,18 +494,30 @@ impl HttpTunnelBuilder {
         self
     }
     /// Removes a header from requests to this edge.
+    /// May be called multiple times to remove multiple headers.
     pub fn remove_request_header(&mut self, name: impl Into<String>) -> &mut Self {
         self.options.request_headers.remove(name);
         self
     }
-    /// Removes a header from responses from this edge.
+    /// Removes a header from responses from this edge, e.g. `Server` or
+    /// `X-Powered-By`.
+    /// May be called multiple times to remove multiple headers.
     pub fn remove_response_header(&mut self, name: impl Into<String>) -> &mut Self {
         self.options.response_headers.remove(name);
         self
     }
 
     /// Adds the provided credentials to the list of basic authentication
-    /// credentials.
+    /// credentials. May be called multiple times to allow multiple accounts.
+    ///
+    /// Passwords must be between 8 and 128 characters, and usernames must be
+    /// non-empty and may not contain a `:`. Invalid credentials cause
+    /// [TunnelBuilder::listen] to fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    ///
+    /// See [Basic Auth] in the ngrok docs for additional details.
+    ///
+    /// [Basic Auth]: https://ngrok.com/docs/http/basic-auth/
     pub fn basic_auth(
         &mut self,
         username: impl Into<String>,
@@ -286,18 +541,57 @@ impl HttpTunnelBuilder {
         self
     }
 
+    /// Limits requests to this edge to `requests` per `per`, grouped by the
+    /// provided [RateLimitKey]. Requests over the limit are rejected at the
+    /// ngrok edge with a `429 Too Many Requests` response.
+    ///
+    /// Both `requests` and `per` must be nonzero, or starting the tunnel will
+    /// fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    pub fn rate_limit(&mut self, requests: u32, per: Duration, by: RateLimitKey) -> &mut Self {
+        self.options.rate_limit = Some(RateLimit { requests, per, by });
+        self
+    }
+
+    /// Redirects plain http requests to the same URL over https with a
+    /// `301 Moved Permanently` response at the ngrok edge.
+    ///
+    /// An https tunnel never receives plain http requests, so this is only
+    /// valid with [Scheme::HTTP]. Starting an https tunnel with it enabled
+    /// will fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    /// Pair it with an https tunnel for the same domain to serve the
+    /// redirected requests. Like [HttpTunnelBuilder::rate_limit], this is
+    /// implemented as a traffic policy rule, added after any rules from
+    /// [HttpTunnelBuilder::traffic_policy].
+    pub fn force_https_redirect(&mut self, redirect: bool) -> &mut Self {
+        self.options.https_redirect = redirect;
+        self
+    }
+
     /// Configures webhook verification for this edge.
-    pub fn webhook_verification(
+    pub fn verify_webhook(
         &mut self,
-        provider: impl Into<String>,
+        provider: WebhookProvider,
         secret: impl Into<String>,
     ) -> &mut Self {
         self.options.webhook_verification = Some(WebhookVerification {
-            provider: provider.into(),
+            provider,
             secret: secret.into().into(),
         });
         self
     }
+    /// Configures webhook verification for this edge.
+    #[deprecated(note = "use `verify_webhook` with a `WebhookProvider`")]
+    pub fn webhook_verification(
+        &mut self,
+        provider: impl Into<String>,
+        secret: impl Into<String>,
+    ) -> &mut Self {
+        let provider = provider.into();
+        let provider = provider.parse().unwrap_or(WebhookProvider::Other(provider));
+        self.verify_webhook(provider, secret)
+    }
 }
 
 #[cfg(test)]
@@ -317,43 +611,275 @@ mod test {
         // pass to a function accepting the trait to avoid
         // "creates a temporary which is freed while still in use"
         tunnel_test(
-            &HttpTunnelBuilder {
-                session: None,
-                options: Default::default(),
+            &HttpTunnelBuilder::without_session()
+                .allow_cidr(ALLOW_CIDR)
+                .deny_cidr(DENY_CIDR)
+                .proxy_proto(ProxyProto::V2)
+                .metadata(METADATA)
+                .pooling_enabled(true)
+                .scheme(Scheme::from_str("hTtPs").unwrap())
+                .domain(DOMAIN)
+                .mutual_tlsca(CA_CERT.into())
+                .mutual_tlsca(CA_CERT2.into())
+                .compression()
+                .websocket_tcp_converter()
+                .circuit_breaker(0.5)
+                .request_header("X-Req-Yup", "true")
+                .response_header("X-Res-Yup", "true")
+                .remove_request_header("X-Req-Nope")
+                .remove_request_header("X-Req-Nope2")
+                .remove_response_header("X-Res-Nope")
+                .remove_response_header("X-Res-Nope2")
+                .oauth(OauthOptions::new("google"))
+                .oauth(
+                    OauthOptions::new("google")
+                        .allow_email("<user>@<domain>")
+                        .allow_domain("<domain>")
+                        .scope("<scope>"),
+                )
+                .oidc(OidcOptions::new("<url>", "<id>", "<secret>"))
+                .oidc(
+                    OidcOptions::new("<url>", "<id>", "<secret>")
+                        .allow_email("<user>@<domain>")
+                        .allow_domain("<domain>")
+                        .scope("<scope>"),
+                )
+                .verify_webhook(WebhookProvider::Twilio, "asdf")
+                .basic_auth("ngrok", "online1line")
+                .forwards_to(TEST_FORWARD)
+                .options,
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker_validation() {
+        let mut opts = HttpOptions::default();
+        assert!(opts.validate().is_ok());
+        opts.circuit_breaker = 1.0;
+        assert!(opts.validate().is_ok());
+        opts.circuit_breaker = 1.5;
+        assert!(opts.validate().is_err());
+        opts.circuit_breaker = -0.1;
+        assert!(opts.validate().is_err());
+        opts.circuit_breaker = f64::NAN;
+        assert!(opts.validate().is_err());
+    }
+
+    #[test]
+    fn test_basic_auth_validation() {
+        let mut opts = HttpOptions::default();
+        opts.basic_auth.push(("ngrok".into(), "online1line".into()));
+        opts.basic_auth.push(("other".into(), "12345678".into()));
+        assert!(opts.validate().is_ok());
+        opts.basic_auth.push(("short".into(), "1234567".into()));
+        assert!(opts.validate().is_err());
+
+        opts.basic_auth = vec![("".into(), "online1line".into())];
+        assert!(opts.validate().is_err());
+        opts.basic_auth = vec![("us:er".into(), "online1line".into())];
+        assert!(opts.validate().is_err());
+        opts.basic_auth = vec![("ngrok".into(), "x".repeat(129))];
+        assert!(opts.validate().is_err());
+    }
+
+    #[test]
+    fn test_scheme_validation() {
+        let mut opts = HttpOptions::default();
+        opts.mutual_tlsca.push(Bytes::from_static(b"ca"));
+        assert!(opts.validate().is_ok());
+        opts.scheme = Scheme::HTTP;
+        assert!(opts.validate().is_err());
+        opts.mutual_tlsca.clear();
+        assert!(opts.validate().is_ok());
+        assert_eq!("http", opts.proto());
+    }
+
+    #[test]
+    fn test_identity_headers() {
+        let request_headers = |builder: &HttpTunnelBuilder| match builder.options.opts() {
+            Some(BindOpts::Http(endpoint)) => endpoint.request_headers,
+            _ => unreachable!(),
+        };
+        let mut builder = HttpTunnelBuilder::without_session();
+        builder.oauth(OauthOptions::new("google"));
+        assert!(request_headers(&builder).is_none());
+
+        builder.oauth(OauthOptions::new("google").pass_identity_headers(false));
+        assert_eq!(IDENTITY_HEADERS, request_headers(&builder).unwrap().remove);
+
+        builder.options.oauth = None;
+        builder.oidc(OidcOptions::new("<url>", "<id>", "<secret>").pass_identity_headers(false));
+        assert_eq!(IDENTITY_HEADERS, request_headers(&builder).unwrap().remove);
+    }
+
+    #[test]
+    fn test_builder_validate() {
+        let mut builder = HttpTunnelBuilder::without_session();
+        assert!(TunnelBuilder::validate(&builder).is_ok());
+        builder.circuit_breaker(1.5);
+        assert!(matches!(
+            TunnelBuilder::validate(&builder),
+            Err(RpcError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_subdomain() {
+        let mut opts = HttpOptions {
+            subdomain: Some("my-app".into()),
+            ..Default::default()
+        };
+        assert!(opts.validate().is_ok());
+        match opts.opts() {
+            Some(BindOpts::Http(endpoint)) => assert_eq!("my-app", endpoint.subdomain),
+            _ => unreachable!(),
+        }
+
+        for subdomain in ["", "my.app", "-app", "my_app"] {
+            opts.subdomain = Some(subdomain.into());
+            assert!(opts.validate().is_err(), "{subdomain}");
+        }
+
+        opts.subdomain = Some("my-app".into());
+        opts.domain = Some("example.ngrok.app".into());
+        assert!(opts.validate().is_err());
+    }
+
+    #[test]
+    fn test_domain_validation() {
+        let mut opts = HttpOptions::default();
+        for domain in ["example.ngrok.app", "*.example.com"] {
+            opts.domain = Some(domain.into());
+            assert!(opts.validate().is_ok(), "{domain}");
+        }
+        for domain in ["", "test domain", "localhost", "*.", "http://example.com"] {
+            opts.domain = Some(domain.into());
+            assert!(opts.validate().is_err(), "{domain}");
+        }
+    }
+
+    #[test]
+    fn test_compression_serialization() {
+        let compression = |opts: &HttpOptions| match opts.opts() {
+            Some(BindOpts::Http(endpoint)) => {
+                serde_json::to_value(endpoint).unwrap()["Compression"].clone()
             }
-            .allow_cidr(ALLOW_CIDR)
-            .deny_cidr(DENY_CIDR)
-            .proxy_proto(ProxyProto::V2)
-            .metadata(METADATA)
-            .scheme(Scheme::from_str("hTtPs").unwrap())
-            .domain(DOMAIN)
-            .mutual_tlsca(CA_CERT.into())
-            .mutual_tlsca(CA_CERT2.into())
-            .compression()
-            .websocket_tcp_conversion()
-            .circuit_breaker(0.5)
-            .request_header("X-Req-Yup", "true")
-            .response_header("X-Res-Yup", "true")
-            .remove_request_header("X-Req-Nope")
-            .remove_response_header("X-Res-Nope")
-            .oauth(OauthOptions::new("google"))
-            .oauth(
-                OauthOptions::new("google")
-                    .allow_email("<user>@<domain>")
-                    .allow_domain("<domain>")
-                    .scope("<scope>"),
-            )
-            .oidc(OidcOptions::new("<url>", "<id>", "<secret>"))
-            .oidc(
-                OidcOptions::new("<url>", "<id>", "<secret>")
-                    .allow_email("<user>@<domain>")
-                    .allow_domain("<domain>")
-                    .scope("<scope>"),
-            )
-            .webhook_verification("twilio", "asdf")
-            .basic_auth("ngrok", "online1line")
-            .forwards_to(TEST_FORWARD)
-            .options,
+            _ => unreachable!("http options always produce http bind opts"),
+        };
+
+        let mut opts = HttpOptions::default();
+        assert_eq!(serde_json::Value::Null, compression(&opts));
+
+        opts.compression = true;
+        assert_eq!(serde_json::json!({}), compression(&opts));
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut opts = HttpOptions::default();
+        assert!(opts.traffic_policy().unwrap().is_empty());
+
+        opts.rate_limit = Some(RateLimit {
+            requests: 10,
+            per: Duration::from_secs(60),
+            by: RateLimitKey::Header("X-Api-Key".into()),
+        });
+        assert!(opts.validate().is_ok());
+        let policy: serde_json::Value =
+            serde_json::from_str(&opts.traffic_policy().unwrap()).unwrap();
+        let config = &policy["inbound"][0]["actions"][0]["config"];
+        assert_eq!(10, config["capacity"]);
+        assert_eq!("60000ms", config["rate"]);
+        assert_eq!("req.headers['x-api-key']", config["bucket_key"][0]);
+
+        opts.common_opts.traffic_policy = Some(PolicySource::Typed(
+            TrafficPolicy::new()
+                .inbound(Rule::new("mine").action(Action::new("deny")))
+                .to_owned(),
+        ));
+        let policy: serde_json::Value =
+            serde_json::from_str(&opts.traffic_policy().unwrap()).unwrap();
+        assert_eq!("mine", policy["inbound"][0]["name"]);
+        assert_eq!("rate limit", policy["inbound"][1]["name"]);
+
+        opts.common_opts.traffic_policy = Some(PolicySource::Raw(
+            "outbound:\n  - actions:\n      - type: add-headers\n".into(),
+        ));
+        let policy: serde_json::Value =
+            serde_json::from_str(&opts.traffic_policy().unwrap()).unwrap();
+        assert_eq!("add-headers", policy["outbound"][0]["actions"][0]["type"]);
+        assert_eq!("rate limit", policy["inbound"][0]["name"]);
+
+        opts.common_opts.traffic_policy = Some(PolicySource::Raw("inbound: [".into()));
+        assert!(opts.traffic_policy().is_err());
+        assert!(opts.validate().is_err());
+
+        opts.rate_limit = Some(RateLimit {
+            requests: 10,
+            per: Duration::ZERO,
+            by: RateLimitKey::Ip,
+        });
+        assert!(opts.validate().is_err());
+    }
+
+    #[test]
+    fn test_https_redirect() {
+        let mut opts = HttpOptions {
+            https_redirect: true,
+            ..Default::default()
+        };
+        assert!(opts.validate().is_err());
+        opts.scheme = Scheme::HTTP;
+        assert!(opts.validate().is_ok());
+
+        opts.rate_limit = Some(RateLimit {
+            requests: 10,
+            per: Duration::from_secs(60),
+            by: RateLimitKey::Ip,
+        });
+        let policy: serde_json::Value =
+            serde_json::from_str(&opts.traffic_policy().unwrap()).unwrap();
+        let rule = &policy["inbound"][0];
+        assert_eq!("https redirect", rule["name"]);
+        assert_eq!("redirect", rule["actions"][0]["type"]);
+        assert_eq!(301, rule["actions"][0]["config"]["status_code"]);
+        assert_eq!("rate limit", policy["inbound"][1]["name"]);
+    }
+
+    #[test]
+    fn test_websocket_tcp_converter() {
+        let mut opts = HttpOptions {
+            scheme: Scheme::HTTP,
+            websocket_tcp_converter: true,
+            ..Default::default()
+        };
+        assert!(opts.validate().is_ok());
+        let Some(BindOpts::Http(endpoint)) = opts.opts() else {
+            panic!("expected http bind opts");
+        };
+        assert!(endpoint.websocket_tcp_converter.is_some());
+
+        opts.https_redirect = true;
+        assert!(opts.validate().is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_webhook_verification() {
+        let mut builder = HttpTunnelBuilder::without_session();
+        let provider = |builder: &HttpTunnelBuilder| {
+            builder
+                .options
+                .webhook_verification
+                .as_ref()
+                .map(|webhook| webhook.provider.clone())
+        };
+        builder.webhook_verification("GitHub", "asdf");
+        assert_eq!(Some(WebhookProvider::Github), provider(&builder));
+        builder.webhook_verification("sendgrid", "asdf");
+        assert_eq!(
+            Some(WebhookProvider::Other("sendgrid".into())),
+            provider(&builder)
         );
     }
 
@@ -366,6 +892,7 @@ mod test {
         let extra = tunnel_cfg.extra();
         assert_eq!(String::default(), *extra.token);
         assert_eq!(METADATA, extra.metadata);
+        assert!(extra.pooling_enabled);
         assert_eq!(String::default(), extra.ip_policy_ref);
 
         assert_eq!("https", tunnel_cfg.proto());
@@ -392,11 +919,17 @@ mod test {
 
             let request_headers = endpoint.request_headers.unwrap();
             assert_eq!(["X-Req-Yup:true"].to_vec(), request_headers.add);
-            assert_eq!(["X-Req-Nope"].to_vec(), request_headers.remove);
+            assert_eq!(
+                ["X-Req-Nope", "X-Req-Nope2"].to_vec(),
+                request_headers.remove
+            );
 
             let response_headers = endpoint.response_headers.unwrap();
             assert_eq!(["X-Res-Yup:true"].to_vec(), response_headers.add);
-            assert_eq!(["X-Res-Nope"].to_vec(), response_headers.remove);
+            assert_eq!(
+                ["X-Res-Nope", "X-Res-Nope2"].to_vec(),
+                response_headers.remove
+            );
 
             let webhook = endpoint.webhook_verification.unwrap();
             assert_eq!("twilio", webhook.provider);
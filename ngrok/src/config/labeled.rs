@@ -37,6 +37,7 @@ impl TunnelConfig for LabeledOptions {
             token: Default::default(),
             ip_policy_ref: Default::default(),
             metadata: self.common_opts.metadata.clone().unwrap_or_default(),
+            pooling_enabled: self.common_opts.pooling_enabled,
         }
     }
     fn proto(&self) -> String {
@@ -48,6 +49,15 @@ impl TunnelConfig for LabeledOptions {
     fn labels(&self) -> HashMap<String, String> {
         self.labels.clone()
     }
+    fn validate(&self) -> Result<(), String> {
+        if self.labels.is_empty() {
+            return Err("labeled tunnels require at least one label".into());
+        }
+        if self.labels.contains_key("") {
+            return Err("label keys must not be empty".into());
+        }
+        Ok(())
+    }
 }
 
 impl_builder! {
@@ -63,7 +73,22 @@ impl LabeledTunnelBuilder {
         self
     }
 
-    /// Add a label, value pair for this tunnel.
+    /// Sets the ForwardsTo string for this tunnel. This can be viewed via the
+    /// API or dashboard.
+    pub fn forwards_to(&mut self, forwards_to: impl Into<String>) -> &mut Self {
+        self.options.common_opts.forwards_to = Some(forwards_to.into());
+        self
+    }
+
+    /// Add a label, value pair for this tunnel. May be called multiple times
+    /// to add multiple labels, with later values replacing earlier ones for
+    /// the same key.
+    ///
+    /// At least one label is required, and keys must be non-empty. Otherwise,
+    /// starting the tunnel will fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    /// Once started, the labels are available via
+    /// [LabelsTunnel::labels](crate::tunnel::LabelsTunnel::labels).
     pub fn label(&mut self, label: impl Into<String>, value: impl Into<String>) -> &mut Self {
         self.options.labels.insert(label.into(), value.into());
         self
@@ -73,26 +98,67 @@ impl LabeledTunnelBuilder {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::internals::{
+        proto::StartTunnelWithLabel,
+        raw_session::testing::{
+            accept_rpc,
+            rpc_client,
+        },
+    };
 
     const METADATA: &str = "testmeta";
     const LABEL_KEY: &str = "edge";
     const LABEL_VAL: &str = "edghts_2IC6RJ6CQnuh7waciWyaGKc50Nt";
 
+    #[tokio::test]
+    async fn test_forwards_proto() {
+        let opts = LabeledTunnelBuilder::without_session()
+            .label(LABEL_KEY, LABEL_VAL)
+            .forwards_to("localhost:8080")
+            .options
+            .clone();
+
+        let (mut client, mut server) = rpc_client().await;
+        tokio::spawn(async move {
+            client
+                .listen_label(
+                    opts.labels(),
+                    opts.extra().metadata,
+                    opts.forwards_to(),
+                    "http2",
+                )
+                .await
+        });
+
+        let (_, req) = accept_rpc::<StartTunnelWithLabel>(&mut server).await;
+        assert_eq!(LABEL_VAL, req["Labels"][LABEL_KEY]);
+        assert_eq!("localhost:8080", req["ForwardsTo"]);
+        assert_eq!("http2", req["ForwardsProto"]);
+    }
+
     #[test]
     fn test_interface_to_proto() {
         // pass to a function accepting the trait to avoid
         // "creates a temporary which is freed while still in use"
         tunnel_test(
-            &LabeledTunnelBuilder {
-                session: None,
-                options: Default::default(),
-            }
-            .metadata(METADATA)
-            .label(LABEL_KEY, LABEL_VAL)
-            .options,
+            &LabeledTunnelBuilder::without_session()
+                .metadata(METADATA)
+                .label(LABEL_KEY, LABEL_VAL)
+                .options,
         );
     }
 
+    #[test]
+    fn test_validate() {
+        let mut opts = LabeledOptions::default();
+        assert!(opts.validate().is_err());
+        opts.labels.insert(LABEL_KEY.into(), LABEL_VAL.into());
+        opts.labels.insert("other".into(), "".into());
+        assert!(opts.validate().is_ok());
+        opts.labels.insert("".into(), LABEL_VAL.into());
+        assert!(opts.validate().is_err());
+    }
+
     fn tunnel_test<C>(tunnel_cfg: &C)
     where
         C: TunnelConfig,
@@ -102,6 +168,7 @@ mod test {
         let extra = tunnel_cfg.extra();
         assert_eq!(String::default(), *extra.token);
         assert_eq!(METADATA, extra.metadata);
+        assert!(!extra.pooling_enabled);
         assert_eq!(String::default(), extra.ip_policy_ref);
 
         assert_eq!("", tunnel_cfg.proto());
@@ -1,6 +1,9 @@
-use crate::internals::proto::{
-    Oauth,
-    SecretString,
+use crate::{
+    config::common::is_valid_domain,
+    internals::proto::{
+        Oauth,
+        SecretString,
+    },
 };
 
 /// Oauth Options configuration
@@ -20,6 +23,8 @@ pub struct OauthOptions {
     allow_domains: Vec<String>,
     /// OAuth scopes to request from the provider.
     scopes: Vec<String>,
+    /// Whether to remove the identity headers added by the edge.
+    pub(crate) strip_identity_headers: bool,
 }
 
 impl OauthOptions {
@@ -48,7 +53,11 @@ impl OauthOptions {
         self.allow_emails.push(email.into());
         self
     }
-    /// Append an email domain to the list of allowed domains.
+    /// Append an email domain to the list of allowed domains, e.g.
+    /// `example.com`.
+    ///
+    /// An invalid domain will cause starting the tunnel to fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
     pub fn allow_domain(&mut self, domain: impl Into<String>) -> &mut Self {
         self.allow_domains.push(domain.into());
         self
@@ -58,6 +67,26 @@ impl OauthOptions {
         self.scopes.push(scope.into());
         self
     }
+
+    /// Configures whether the ngrok edge passes the authenticated user's
+    /// identity to the upstream service. Defaults to `true`.
+    ///
+    /// When enabled, requests are forwarded with the `ngrok-auth-user-email`,
+    /// `ngrok-auth-user-id`, and `ngrok-auth-user-name` headers set from the
+    /// user's identity claims. These are always set by the edge, so a client
+    /// can't spoof them. When disabled, they're removed before the request
+    /// is forwarded.
+    pub fn pass_identity_headers(&mut self, pass: bool) -> &mut Self {
+        self.strip_identity_headers = !pass;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self.allow_domains.iter().find(|d| !is_valid_domain(d)) {
+            Some(domain) => Err(format!("invalid oauth allowed domain: {domain:?}")),
+            None => Ok(()),
+        }
+    }
 }
 
 // transform into the wire protocol format
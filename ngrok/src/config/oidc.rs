@@ -1,6 +1,11 @@
-use crate::internals::proto::{
-    Oidc,
-    SecretString,
+use url::Url;
+
+use crate::{
+    config::common::is_valid_domain,
+    internals::proto::{
+        Oidc,
+        SecretString,
+    },
 };
 
 /// Oidc Options configuration
@@ -12,10 +17,16 @@ pub struct OidcOptions {
     allow_emails: Vec<String>,
     allow_domains: Vec<String>,
     scopes: Vec<String>,
+    pub(crate) strip_identity_headers: bool,
 }
 
 impl OidcOptions {
     /// Create a new [OidcOptions] with the given issuer and client information.
+    ///
+    /// Any OIDC-compliant identity provider, such as Okta, Auth0, or Keycloak,
+    /// may be used. If the issuer isn't a valid url, starting the tunnel will
+    /// fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
     pub fn new(
         issuer_url: impl Into<String>,
         client_id: impl Into<String>,
@@ -44,6 +55,23 @@ impl OidcOptions {
         self.scopes.push(scope.into());
         self
     }
+
+    /// Configures whether the ngrok edge passes the authenticated user's
+    /// identity to the upstream service, as with
+    /// [OauthOptions::pass_identity_headers](crate::config::OauthOptions::pass_identity_headers).
+    pub fn pass_identity_headers(&mut self, pass: bool) -> &mut Self {
+        self.strip_identity_headers = !pass;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        Url::parse(&self.issuer_url)
+            .map_err(|e| format!("invalid oidc issuer url {:?}: {e}", self.issuer_url))?;
+        match self.allow_domains.iter().find(|d| !is_valid_domain(d)) {
+            Some(domain) => Err(format!("invalid oidc allowed domain: {domain:?}")),
+            None => Ok(()),
+        }
+    }
 }
 
 // transform into the wire protocol format
@@ -60,3 +88,21 @@ impl From<OidcOptions> for Oidc {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut opts = OidcOptions::new("https://example.okta.com", "id", "secret");
+        assert!(opts.validate().is_ok());
+        opts.allow_domain("example.com");
+        assert!(opts.validate().is_ok());
+        opts.allow_domain("not a domain");
+        assert!(opts.validate().is_err());
+
+        let opts = OidcOptions::new("example.okta.com", "id", "secret");
+        assert!(opts.validate().is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    net::IpAddr,
+};
 
 use async_trait::async_trait;
 
@@ -7,10 +11,15 @@ use super::{
     TunnelBuilder,
 };
 use crate::{
-    config::common::{
-        default_forwards_to,
-        CommonOpts,
-        TunnelConfig,
+    config::{
+        common::{
+            default_forwards_to,
+            is_valid_domain,
+            CommonOpts,
+            TunnelConfig,
+        },
+        traffic_policy::PolicySource,
+        TrafficPolicy,
     },
     internals::proto::{
         self,
@@ -41,6 +50,7 @@ impl TunnelConfig for TcpOptions {
             token: Default::default(),
             ip_policy_ref: Default::default(),
             metadata: self.common_opts.metadata.clone().unwrap_or_default(),
+            pooling_enabled: self.common_opts.pooling_enabled,
         }
     }
     fn proto(&self) -> String {
@@ -56,27 +66,49 @@ impl TunnelConfig for TcpOptions {
         tcp_endpoint.proxy_proto = self.common_opts.proxy_proto;
 
         tcp_endpoint.ip_restriction = self.common_opts.ip_restriction();
+        // A policy that fails to parse has already been rejected by validate.
+        tcp_endpoint.traffic_policy = self.common_opts.traffic_policy().unwrap_or_default();
 
         Some(BindOpts::Tcp(tcp_endpoint))
     }
     fn labels(&self) -> HashMap<String, String> {
         HashMap::new()
     }
+    fn validate(&self) -> Result<(), String> {
+        if let Some(remote_addr) = &self.remote_addr {
+            if !is_valid_remote_addr(remote_addr) {
+                return Err(format!("invalid tcp remote address: {remote_addr:?}"));
+            }
+        }
+        self.common_opts.validate()
+    }
+}
+
+// Check that a remote address is a host and non-zero port, as reserved TCP
+// addresses are.
+fn is_valid_remote_addr(addr: &str) -> bool {
+    match addr.rsplit_once(':') {
+        Some((host, port)) => {
+            matches!(port.parse::<u16>(), Ok(port) if port != 0)
+                && (is_valid_domain(host) || host.parse::<IpAddr>().is_ok())
+        }
+        None => false,
+    }
 }
 
 impl_builder! {
     /// A builder for a tunnel backing a TCP endpoint.
-    TcpTunnelBuilder, TcpOptions, TcpTunnel
+    TcpTunnelBuilder, TcpOptions, TcpTunnel, endpoint
 }
 
 /// The options for a TCP edge.
 impl TcpTunnelBuilder {
-    /// Add the provided CIDR to the allowlist.
+    /// Add the provided CIDR to the allowlist. Invalid CIDRs fail at listen time.
     pub fn allow_cidr(&mut self, cidr: impl Into<String>) -> &mut Self {
         self.options.common_opts.cidr_restrictions.allow(cidr);
         self
     }
-    /// Add the provided CIDR to the denylist.
+    /// Add the provided CIDR to the denylist. Invalid CIDRs fail at listen time.
     pub fn deny_cidr(&mut self, cidr: impl Into<String>) -> &mut Self {
         self.options.common_opts.cidr_restrictions.deny(cidr);
         self
@@ -86,6 +118,19 @@ impl TcpTunnelBuilder {
         self.options.common_opts.proxy_proto = proxy_proto;
         self
     }
+    /// Sets the traffic policy to apply at the ngrok edge.
+    pub fn traffic_policy(&mut self, policy: impl Borrow<TrafficPolicy>) -> &mut Self {
+        self.options.common_opts.traffic_policy =
+            Some(PolicySource::Typed(policy.borrow().to_owned()));
+        self
+    }
+
+    /// Sets the traffic policy from a JSON or YAML document, such as one copied
+    /// from the ngrok dashboard.
+    pub fn traffic_policy_str(&mut self, policy: impl Into<String>) -> &mut Self {
+        self.options.common_opts.traffic_policy = Some(PolicySource::Raw(policy.into()));
+        self
+    }
     /// Sets the opaque metadata string for this tunnel.
     pub fn metadata(&mut self, metadata: impl Into<String>) -> &mut Self {
         self.options.common_opts.metadata = Some(metadata.into());
@@ -97,7 +142,14 @@ impl TcpTunnelBuilder {
         self.options.common_opts.forwards_to = Some(forwards_to.into());
         self
     }
-    /// Sets the TCP address to request for this edge.
+    /// Sets the TCP address to request for this edge, as a `host:port` pair
+    /// such as `1.tcp.ngrok.io:12345`. It must be one of the account's
+    /// reserved TCP addresses.
+    ///
+    /// Without one, the ngrok service assigns a random address. Either way,
+    /// the tunnel keeps its address when the session reconnects. An address
+    /// that isn't a host and port will cause starting the tunnel to fail
+    /// with [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
     pub fn remote_addr(&mut self, remote_addr: impl Into<String>) -> &mut Self {
         self.options.remote_addr = Some(remote_addr.into());
         self
@@ -119,20 +171,36 @@ mod test {
         // pass to a function accepting the trait to avoid
         // "creates a temporary which is freed while still in use"
         tunnel_test(
-            &TcpTunnelBuilder {
-                session: None,
-                options: Default::default(),
-            }
-            .allow_cidr(ALLOW_CIDR)
-            .deny_cidr(DENY_CIDR)
-            .proxy_proto(ProxyProto::V2)
-            .metadata(METADATA)
-            .remote_addr(REMOTE_ADDR)
-            .forwards_to(TEST_FORWARD)
-            .options,
+            &TcpTunnelBuilder::without_session()
+                .allow_cidr(ALLOW_CIDR)
+                .deny_cidr(DENY_CIDR)
+                .proxy_proto(ProxyProto::V2)
+                .metadata(METADATA)
+                .pooling_enabled(true)
+                .remote_addr(REMOTE_ADDR)
+                .forwards_to(TEST_FORWARD)
+                .options,
         );
     }
 
+    #[test]
+    fn test_remote_addr_validation() {
+        let validate = |addr: &str| {
+            TcpOptions {
+                remote_addr: Some(addr.into()),
+                ..Default::default()
+            }
+            .validate()
+        };
+        assert!(validate(REMOTE_ADDR).is_ok());
+        assert!(validate("1.2.3.4:1337").is_ok());
+        assert!(validate("4.tcp.ngrok.io").is_err());
+        assert!(validate("4.tcp.ngrok.io:0").is_err());
+        assert!(validate("4.tcp.ngrok.io:65536").is_err());
+        assert!(validate(":1337").is_err());
+        assert!(validate("not a host:1337").is_err());
+    }
+
     fn tunnel_test<C>(tunnel_cfg: &C)
     where
         C: TunnelConfig,
@@ -142,6 +210,7 @@ mod test {
         let extra = tunnel_cfg.extra();
         assert_eq!(String::default(), *extra.token);
         assert_eq!(METADATA, extra.metadata);
+        assert!(extra.pooling_enabled);
         assert_eq!(String::default(), extra.ip_policy_ref);
 
         assert_eq!("tcp", tunnel_cfg.proto());
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+};
 
 use async_trait::async_trait;
 use bytes::{
@@ -11,16 +14,20 @@ use super::{
     TunnelBuilder,
 };
 use crate::{
-    config::common::{
-        default_forwards_to,
-        CommonOpts,
-        TunnelConfig,
+    config::{
+        common::{
+            default_forwards_to,
+            CommonOpts,
+            TunnelConfig,
+        },
+        traffic_policy::PolicySource,
+        TrafficPolicy,
     },
     internals::proto::{
         self,
         BindExtra,
         BindOpts,
-        TlsTermination,
+        TlsTermination as TlsTerminationProto,
     },
     session::RpcError,
     tunnel::TlsTunnel,
@@ -33,8 +40,25 @@ struct TlsOptions {
     pub(crate) common_opts: CommonOpts,
     pub(crate) domain: Option<String>,
     pub(crate) mutual_tlsca: Vec<bytes::Bytes>,
-    pub(crate) key_pem: Option<bytes::Bytes>,
-    pub(crate) cert_pem: Option<bytes::Bytes>,
+    pub(crate) termination: TlsTermination,
+}
+
+/// Where TLS is terminated for connections to a TLS edge.
+#[derive(Debug, Clone, Default)]
+pub enum TlsTermination {
+    /// Terminate TLS at the ngrok edge with the provided certificate and key
+    /// in PEM format.
+    Edge {
+        /// The certificate to present to clients, in PEM format.
+        cert_pem: Bytes,
+        /// The private key for the certificate, in PEM format.
+        key_pem: Bytes,
+    },
+    /// Pass TLS through the ngrok edge untouched, so that it's terminated by
+    /// the local service. This allows the local service to see the client's
+    /// certificate, if any.
+    #[default]
+    Passthrough,
 }
 
 impl TunnelConfig for TlsOptions {
@@ -49,6 +73,7 @@ impl TunnelConfig for TlsOptions {
             token: Default::default(),
             ip_policy_ref: Default::default(),
             metadata: self.common_opts.metadata.clone().unwrap_or_default(),
+            pooling_enabled: self.common_opts.pooling_enabled,
         }
     }
     fn proto(&self) -> String {
@@ -64,18 +89,18 @@ impl TunnelConfig for TlsOptions {
         }
         tls_endpoint.proxy_proto = self.common_opts.proxy_proto;
 
-        // doing some backflips to check both cert_pem and key_pem are set, and avoid unwrapping
-        let tls_termination = self
-            .cert_pem
-            .as_ref()
-            .zip(self.key_pem.as_ref())
-            .map(|(c, k)| TlsTermination {
-                cert: c.to_vec(),
-                key: k.to_vec().into(),
+        let tls_termination = match &self.termination {
+            TlsTermination::Edge { cert_pem, key_pem } => Some(TlsTerminationProto {
+                cert: cert_pem.to_vec(),
+                key: key_pem.to_vec().into(),
                 sealed_key: Vec::new(),
-            });
+            }),
+            TlsTermination::Passthrough => None,
+        };
 
         tls_endpoint.ip_restriction = self.common_opts.ip_restriction();
+        // A policy that fails to parse has already been rejected by validate.
+        tls_endpoint.traffic_policy = self.common_opts.traffic_policy().unwrap_or_default();
         tls_endpoint.mutual_tls_at_edge =
             (!self.mutual_tlsca.is_empty()).then_some(self.mutual_tlsca.as_slice().into());
         tls_endpoint.tls_termination = tls_termination;
@@ -85,20 +110,28 @@ impl TunnelConfig for TlsOptions {
     fn labels(&self) -> HashMap<String, String> {
         HashMap::new()
     }
+    fn validate(&self) -> Result<(), String> {
+        self.common_opts.validate()?;
+        if matches!(self.termination, TlsTermination::Passthrough) && !self.mutual_tlsca.is_empty()
+        {
+            return Err("mutual tls requires tls termination at the edge".into());
+        }
+        Ok(())
+    }
 }
 
 impl_builder! {
     /// A builder for a tunnel backing a TCP endpoint.
-    TlsTunnelBuilder, TlsOptions, TlsTunnel
+    TlsTunnelBuilder, TlsOptions, TlsTunnel, endpoint
 }
 
 impl TlsTunnelBuilder {
-    /// Add the provided CIDR to the allowlist.
+    /// Add the provided CIDR to the allowlist. Invalid CIDRs fail at listen time.
     pub fn allow_cidr(&mut self, cidr: impl Into<String>) -> &mut Self {
         self.options.common_opts.cidr_restrictions.allow(cidr);
         self
     }
-    /// Add the provided CIDR to the denylist.
+    /// Add the provided CIDR to the denylist. Invalid CIDRs fail at listen time.
     pub fn deny_cidr(&mut self, cidr: impl Into<String>) -> &mut Self {
         self.options.common_opts.cidr_restrictions.deny(cidr);
         self
@@ -108,6 +141,19 @@ impl TlsTunnelBuilder {
         self.options.common_opts.proxy_proto = proxy_proto;
         self
     }
+    /// Sets the traffic policy to apply at the ngrok edge.
+    pub fn traffic_policy(&mut self, policy: impl Borrow<TrafficPolicy>) -> &mut Self {
+        self.options.common_opts.traffic_policy =
+            Some(PolicySource::Typed(policy.borrow().to_owned()));
+        self
+    }
+
+    /// Sets the traffic policy from a JSON or YAML document, such as one copied
+    /// from the ngrok dashboard.
+    pub fn traffic_policy_str(&mut self, policy: impl Into<String>) -> &mut Self {
+        self.options.common_opts.traffic_policy = Some(PolicySource::Raw(policy.into()));
+        self
+    }
     /// Sets the opaque metadata string for this tunnel.
     pub fn metadata(&mut self, metadata: impl Into<String>) -> &mut Self {
         self.options.common_opts.metadata = Some(metadata.into());
@@ -128,18 +174,37 @@ impl TlsTunnelBuilder {
     /// Adds a certificate in PEM format to use for mutual TLS authentication.
     ///
     /// These will be used to authenticate client certificates for requests at
-    /// the ngrok edge.
+    /// the ngrok edge. May be called multiple times to trust multiple CAs.
+    ///
+    /// Client certificates can only be verified where TLS is terminated, so
+    /// this requires terminating TLS at the ngrok edge via
+    /// [TlsTunnelBuilder::tls_termination]. When TLS is passed through to the
+    /// local service instead, the edge never sees the client's certificate,
+    /// and verifying it is left to the local service. Setting a CA without
+    /// edge termination fails at listen time with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
     pub fn mutual_tlsca(&mut self, mutual_tlsca: Bytes) -> &mut Self {
         self.options.mutual_tlsca.push(mutual_tlsca);
         self
     }
 
+    /// Sets where TLS is terminated for connections to this edge. Defaults to
+    /// [TlsTermination::Passthrough].
+    ///
+    /// With passthrough, forwarding to a `tls` url via
+    /// [TunnelExt::forward](crate::prelude::TunnelExt::forward) sends the
+    /// client's TLS stream to the local service as-is rather than starting a
+    /// new TLS session with it.
+    pub fn tls_termination(&mut self, termination: TlsTermination) -> &mut Self {
+        self.options.termination = termination;
+        self
+    }
+
     /// Sets the key and certificate in PEM format for TLS termination at the
     /// ngrok edge.
+    #[deprecated(note = "use `tls_termination` with `TlsTermination::Edge`")]
     pub fn termination(&mut self, cert_pem: Bytes, key_pem: Bytes) -> &mut Self {
-        self.options.key_pem = Some(key_pem);
-        self.options.cert_pem = Some(cert_pem);
-        self
+        self.tls_termination(TlsTermination::Edge { cert_pem, key_pem })
     }
 }
 
@@ -162,20 +227,21 @@ mod test {
         // pass to a function accepting the trait to avoid
         // "creates a temporary which is freed while still in use"
         tunnel_test(
-            &TlsTunnelBuilder {
-                session: None,
-                options: Default::default(),
-            }
-            .allow_cidr(ALLOW_CIDR)
-            .deny_cidr(DENY_CIDR)
-            .proxy_proto(ProxyProto::V2)
-            .metadata(METADATA)
-            .domain(DOMAIN)
-            .mutual_tlsca(CA_CERT.into())
-            .mutual_tlsca(CA_CERT2.into())
-            .termination(CERT.into(), KEY.into())
-            .forwards_to(TEST_FORWARD)
-            .options,
+            &TlsTunnelBuilder::without_session()
+                .allow_cidr(ALLOW_CIDR)
+                .deny_cidr(DENY_CIDR)
+                .proxy_proto(ProxyProto::V2)
+                .metadata(METADATA)
+                .pooling_enabled(true)
+                .domain(DOMAIN)
+                .mutual_tlsca(CA_CERT.into())
+                .mutual_tlsca(CA_CERT2.into())
+                .tls_termination(TlsTermination::Edge {
+                    cert_pem: CERT.into(),
+                    key_pem: KEY.into(),
+                })
+                .forwards_to(TEST_FORWARD)
+                .options,
         );
     }
 
@@ -188,6 +254,7 @@ mod test {
         let extra = tunnel_cfg.extra();
         assert_eq!(String::default(), *extra.token);
         assert_eq!(METADATA, extra.metadata);
+        assert!(extra.pooling_enabled);
         assert_eq!(String::default(), extra.ip_policy_ref);
 
         assert_eq!("tls", tunnel_cfg.proto());
@@ -217,4 +284,38 @@ mod test {
 
         assert_eq!(HashMap::new(), tunnel_cfg.labels());
     }
+
+    #[test]
+    fn test_passthrough() {
+        let opts = TlsOptions::default();
+        assert!(matches!(opts.termination, TlsTermination::Passthrough));
+        match opts.opts() {
+            Some(BindOpts::Tls(endpoint)) => assert!(endpoint.tls_termination.is_none()),
+            _ => unreachable!("tls options always produce tls bind opts"),
+        }
+    }
+
+    #[test]
+    fn test_mutual_tls_validation() {
+        let mut opts = TlsOptions::default();
+        assert!(opts.validate().is_ok());
+        opts.mutual_tlsca.push(CA_CERT.into());
+        assert!(opts.validate().is_err());
+        opts.termination = TlsTermination::Edge {
+            cert_pem: CERT.into(),
+            key_pem: KEY.into(),
+        };
+        assert!(opts.validate().is_ok());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_termination() {
+        let mut builder = TlsTunnelBuilder::without_session();
+        builder.termination(CERT.into(), KEY.into());
+        assert!(matches!(
+            builder.options.termination,
+            TlsTermination::Edge { .. }
+        ));
+    }
 }
@@ -0,0 +1,217 @@
+use std::borrow::Borrow;
+
+use serde::Serialize;
+
+/// A traffic policy to apply to connections and requests at the ngrok edge.
+///
+/// Policies are made up of [Rule]s, which are applied to inbound traffic
+/// before it's sent through the tunnel, or to outbound traffic on its way
+/// back to the client.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrafficPolicy {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    inbound: Vec<Rule>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    outbound: Vec<Rule>,
+}
+
+impl TrafficPolicy {
+    /// Create a new, empty [TrafficPolicy].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Append a rule to apply to inbound traffic.
+    pub fn inbound(&mut self, rule: impl Borrow<Rule>) -> &mut Self {
+        self.inbound.push(rule.borrow().to_owned());
+        self
+    }
+    /// Append a rule to apply to outbound traffic.
+    pub fn outbound(&mut self, rule: impl Borrow<Rule>) -> &mut Self {
+        self.outbound.push(rule.borrow().to_owned());
+        self
+    }
+
+    /// Serialize the policy to the JSON format understood by the ngrok edge.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("traffic policies are always serializable")
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        self.inbound
+            .iter()
+            .chain(&self.outbound)
+            .try_for_each(Rule::validate)
+    }
+}
+
+// A traffic policy as provided to a tunnel builder, either typed or as a raw
+// JSON or YAML document.
+#[derive(Debug, Clone)]
+pub(crate) enum PolicySource {
+    Typed(TrafficPolicy),
+    Raw(String),
+}
+
+impl PolicySource {
+    // Get the policy as a JSON object, failing if a raw policy doesn't parse
+    // to one.
+    pub(crate) fn to_value(&self) -> Result<serde_json::Value, String> {
+        match self {
+            PolicySource::Typed(policy) => serde_json::to_value(policy).map_err(|e| e.to_string()),
+            // YAML is a superset of JSON, so this handles both.
+            PolicySource::Raw(raw) => match serde_yaml::from_str(raw)
+                .map_err(|e| format!("invalid traffic policy: {e}"))?
+            {
+                value @ serde_json::Value::Object(_) => Ok(value),
+                _ => Err("invalid traffic policy: expected a JSON or YAML object".into()),
+            },
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self {
+            PolicySource::Typed(policy) => policy.validate(),
+            PolicySource::Raw(_) => self.to_value().map(drop),
+        }
+    }
+}
+
+/// A traffic policy rule, which runs its [Action]s on any traffic matching all
+/// of its expressions.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Rule {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    expressions: Vec<String>,
+    actions: Vec<Action>,
+}
+
+impl Rule {
+    /// Create a new [Rule] with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Rule {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Append an expression that traffic must match for this rule to apply.
+    ///
+    /// A rule without any expressions applies to all traffic. Expressions
+    /// must be non-empty, or starting the tunnel will fail with
+    /// [RpcError::InvalidConfig](crate::session::RpcError::InvalidConfig).
+    pub fn expression(&mut self, expression: impl Into<String>) -> &mut Self {
+        self.expressions.push(expression.into());
+        self
+    }
+    /// Append an action to run on traffic matching this rule.
+    pub fn action(&mut self, action: impl Borrow<Action>) -> &mut Self {
+        self.actions.push(action.borrow().to_owned());
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.expressions.iter().any(|e| e.trim().is_empty()) {
+            return Err(format!(
+                "traffic policy rule {:?} has an empty expression",
+                self.name
+            ));
+        }
+        if self.actions.iter().any(|a| a.kind.is_empty()) {
+            return Err(format!(
+                "traffic policy rule {:?} has an action without a type",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A traffic policy action, such as `deny` or `add-headers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Action {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config: Option<serde_json::Value>,
+}
+
+impl Action {
+    /// Create a new [Action] of the given type.
+    pub fn new(kind: impl Into<String>) -> Self {
+        Action {
+            kind: kind.into(),
+            config: None,
+        }
+    }
+
+    /// Set the action-specific configuration.
+    pub fn config(&mut self, config: serde_json::Value) -> &mut Self {
+        self.config = Some(config);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let policy = TrafficPolicy::new()
+            .inbound(
+                Rule::new("deny bots")
+                    .expression("req.user_agent.contains('bot')")
+                    .action(Action::new("deny").config(serde_json::json!({ "status_code": 403 }))),
+            )
+            .outbound(Rule::new("").action(Action::new("add-headers")))
+            .to_owned();
+        assert!(policy.validate().is_ok());
+
+        let json: serde_json::Value = serde_json::from_str(&policy.to_json()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "inbound": [{
+                    "name": "deny bots",
+                    "expressions": ["req.user_agent.contains('bot')"],
+                    "actions": [{ "type": "deny", "config": { "status_code": 403 } }],
+                }],
+                "outbound": [{
+                    "actions": [{ "type": "add-headers" }],
+                }],
+            }),
+            json
+        );
+
+        assert_eq!("{}", TrafficPolicy::new().to_json());
+    }
+
+    #[test]
+    fn test_raw_policy() {
+        let json = PolicySource::Raw(r#"{"inbound": [{"actions": [{"type": "deny"}]}]}"#.into());
+        let yaml = PolicySource::Raw("inbound:\n  - actions:\n      - type: deny\n".into());
+        assert!(json.validate().is_ok());
+        assert!(yaml.validate().is_ok());
+        assert_eq!(json.to_value(), yaml.to_value());
+
+        assert!(PolicySource::Raw("inbound: [".into()).validate().is_err());
+        assert!(PolicySource::Raw("just a string".into())
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let policy = TrafficPolicy::new()
+            .inbound(Rule::new("empty").expression(" "))
+            .to_owned();
+        assert!(policy.validate().is_err());
+
+        let policy = TrafficPolicy::new()
+            .outbound(Rule::new("untyped").action(Action::new("")))
+            .to_owned();
+        assert!(policy.validate().is_err());
+    }
+}
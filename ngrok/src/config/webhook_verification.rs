@@ -1,26 +1,99 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
 use crate::internals::proto::{
     SecretString,
     WebhookVerification as WebhookProto,
 };
 
+/// Error representing an unrecognized webhook provider name.
+#[derive(Debug, Clone, Error)]
+#[error("unknown webhook provider: {}", .0)]
+pub struct InvalidWebhookProvider(String);
+
+/// A webhook provider whose deliveries can be verified at the ngrok edge.
+///
+/// Parsing a name only succeeds for the providers with a variant here. Others
+/// supported by ngrok can be named explicitly with [WebhookProvider::Other].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WebhookProvider {
+    /// GitHub webhooks.
+    Github,
+    /// Slack webhooks.
+    Slack,
+    /// Stripe webhooks.
+    Stripe,
+    /// Twilio webhooks.
+    Twilio,
+    /// Any other provider supported by ngrok, by its name in the ngrok docs.
+    Other(String),
+}
+
+impl WebhookProvider {
+    /// Returns the name of the provider as understood by the ngrok edge.
+    pub fn as_str(&self) -> &str {
+        match self {
+            WebhookProvider::Github => "github",
+            WebhookProvider::Slack => "slack",
+            WebhookProvider::Stripe => "stripe",
+            WebhookProvider::Twilio => "twilio",
+            WebhookProvider::Other(name) => name,
+        }
+    }
+}
+
+impl FromStr for WebhookProvider {
+    type Err = InvalidWebhookProvider;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use WebhookProvider::*;
+        Ok(match s.to_lowercase().as_str() {
+            "github" => Github,
+            "slack" => Slack,
+            "stripe" => Stripe,
+            "twilio" => Twilio,
+            _ => return Err(InvalidWebhookProvider(s.into())),
+        })
+    }
+}
+
 /// Configuration for webhook verification.
 #[derive(Clone)]
 pub(crate) struct WebhookVerification {
     /// The webhook provider
-    pub(crate) provider: String,
+    pub(crate) provider: WebhookProvider,
     /// The secret for verifying webhooks from this provider.
     pub(crate) secret: SecretString,
 }
 
-impl WebhookVerification {}
-
 // transform into the wire protocol format
 impl From<WebhookVerification> for WebhookProto {
     fn from(wv: WebhookVerification) -> Self {
         WebhookProto {
-            provider: wv.provider,
+            provider: wv.provider.as_str().into(),
             secret: wv.secret,
             sealed_secret: vec![], // unused in this context
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_provider_from_str() {
+        assert_eq!(WebhookProvider::Stripe, "stripe".parse().unwrap());
+        assert_eq!(WebhookProvider::Github, "GitHub".parse().unwrap());
+        assert_eq!(
+            "slack",
+            WebhookProvider::from_str("Slack").unwrap().as_str()
+        );
+        assert!(WebhookProvider::from_str("sendgrid").is_err());
+        assert_eq!(
+            "sendgrid",
+            WebhookProvider::Other("sendgrid".into()).as_str()
+        );
+    }
+}
@@ -261,6 +261,8 @@ pub struct Bind<T> {
     pub client_id: String,
     pub proto: String,
     pub forwards_to: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub forwards_proto: String,
     pub opts: T,
     pub extra: BindExtra,
 }
@@ -281,6 +283,8 @@ pub struct BindExtra {
     #[serde(rename = "IPPolicyRef")]
     pub ip_policy_ref: String,
     pub metadata: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub pooling_enabled: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -309,6 +313,8 @@ rpc_req!(Bind<T>, BindResp<T>, BIND_REQ; T: std::fmt::Debug + Serialize + Deseri
 pub struct StartTunnelWithLabel {
     pub labels: HashMap<String, String>,
     pub forwards_to: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub forwards_proto: String,
     pub metadata: String,
 }
 
@@ -349,6 +355,9 @@ pub struct ProxyHeader {
     pub edge_type: EdgeType,
     #[serde(rename = "PassthroughTLS")]
     pub passthrough_tls: bool,
+    // The header exactly as it was received, minus the length prefix.
+    #[serde(skip)]
+    pub raw: Vec<u8>,
 }
 
 #[derive(Error, Debug)]
@@ -375,15 +384,23 @@ impl ProxyHeader {
 
         debug!(?header, "read header");
 
-        Ok(serde_json::from_str(&header)?)
+        let mut parsed: ProxyHeader = serde_json::from_str(&header)?;
+        parsed.raw = header.into_bytes();
+        Ok(parsed)
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// The type of edge that a tunnel connection arrived through.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum EdgeType {
+    /// The edge type wasn't provided, e.g. for tunnels not started via an edge.
     Undefined,
+    /// A TCP edge.
     Tcp,
+    /// A TLS edge.
     Tls,
+    /// An HTTPS edge.
     Https,
 }
 
@@ -400,7 +417,7 @@ impl FromStr for EdgeType {
 }
 
 impl EdgeType {
-    pub fn as_str(self) -> &'static str {
+    pub(crate) fn as_str(self) -> &'static str {
         match self {
             EdgeType::Undefined => "0",
             EdgeType::Tcp => "1",
@@ -472,6 +489,9 @@ pub type RestartResp = CommandResp;
 rpc_req!(Restart, RestartResp, RESTART_REQ);
 
 /// A request from the ngrok dashboard for the agent to update itself.
+///
+/// This is about the agent's own version; there's no request for updating a
+/// tunnel's configuration once it's been bound.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "PascalCase")]
 pub struct Update {
@@ -619,6 +639,8 @@ pub struct HttpEndpoint {
     pub response_headers: Option<Headers>,
     #[serde(rename = "WebsocketTCPConverter")]
     pub websocket_tcp_converter: Option<WebsocketTcpConverter>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub traffic_policy: String,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -736,6 +758,8 @@ pub struct TcpEndpoint {
     pub proxy_proto: ProxyProto,
     #[serde(rename = "IPRestriction")]
     pub ip_restriction: Option<IpRestriction>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub traffic_policy: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -753,6 +777,8 @@ pub struct TlsEndpoint {
     pub tls_termination: Option<TlsTermination>,
     #[serde(rename = "IPRestriction")]
     pub ip_restriction: Option<IpRestriction>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub traffic_policy: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -806,4 +832,16 @@ mod test {
 
         assert_eq!(serde_json::to_string(&p).unwrap(), "2");
     }
+
+    #[tokio::test]
+    async fn test_proxy_header_raw() {
+        let json = r#"{"Id":"tun","ClientAddr":"1.2.3.4:5678","Proto":"tls","EdgeType":"2","PassthroughTLS":true,"Hostname":"app.example.com"}"#;
+        let mut buf = (json.len() as i64).to_le_bytes().to_vec();
+        buf.extend_from_slice(json.as_bytes());
+
+        // Fields without their own accessor are still in the raw header.
+        let header = ProxyHeader::read_from_stream(&buf[..]).await.unwrap();
+        assert!(header.passthrough_tls);
+        assert_eq!(json.as_bytes(), &header.raw[..]);
+    }
 }
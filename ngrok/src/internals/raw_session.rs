@@ -8,6 +8,7 @@ use std::{
         DerefMut,
     },
     sync::Arc,
+    time::Duration,
 };
 
 use async_trait::async_trait;
@@ -99,6 +100,13 @@ pub enum RpcError {
     /// There was an error in the RPC response.
     #[error("rpc error response:\n{0}")]
     Response(ErrResp),
+    /// The tunnel configuration was invalid, so no RPC was made.
+    #[error("invalid tunnel configuration: {0}")]
+    InvalidConfig(String),
+    /// The tunnel started, but its URL couldn't be written to the file
+    /// configured for it, so it was closed again.
+    #[error("failed to write tunnel url file")]
+    UrlFile(#[source] io::Error),
 }
 
 impl Error for RpcError {
@@ -117,6 +125,78 @@ impl Error for RpcError {
     }
 }
 
+impl RpcError {
+    /// Classifies the reason the ngrok service refused to start a tunnel.
+    ///
+    /// Returns [None] if the error isn't a response from the ngrok service,
+    /// such as a transport failure or an invalid tunnel configuration.
+    pub fn bind_error(&self) -> Option<BindError> {
+        match self {
+            RpcError::Response(resp) => Some(resp.into()),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the call might succeed if it's retried later.
+    ///
+    /// This is the case when the request never reached the ngrok service,
+    /// because its stream couldn't be opened or the request couldn't be
+    /// sent, such as while the session is reconnecting, and for
+    /// [BindError::RateLimited] responses. Once a request has been sent, the
+    /// service may have acted on it even if no response arrived, so retrying
+    /// could start a duplicate tunnel. Other responses, such as rejected
+    /// credentials, and invalid configurations won't resolve on their own.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RpcError::Open(_) | RpcError::Send(_) => true,
+            RpcError::Response(_) => self.bind_error() == Some(BindError::RateLimited),
+            RpcError::Transport(_)
+            | RpcError::Receive(_)
+            | RpcError::InvalidResponse(_)
+            | RpcError::InvalidConfig(_)
+            | RpcError::UrlFile(_) => false,
+        }
+    }
+}
+
+/// The reason the ngrok service refused to start a tunnel, derived from the
+/// error code in its response. See [RpcError::bind_error].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BindError {
+    /// The session's authtoken or credentials were rejected.
+    #[error("authentication failed")]
+    AuthFailed,
+    /// The requested domain or address is already bound by another session.
+    #[error("domain already in use")]
+    DomainInUse,
+    /// The account isn't permitted to start the tunnel as configured, such as
+    /// binding a domain it hasn't reserved.
+    #[error("forbidden")]
+    Forbidden,
+    /// The account has reached its limit of sessions or tunnels.
+    #[error("rate limited")]
+    RateLimited,
+    /// Any other error, with the message from the ngrok service.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<&ErrResp> for BindError {
+    fn from(resp: &ErrResp) -> Self {
+        let code = resp
+            .error_code()
+            .map(|code| code.trim_start_matches("ERR_NGROK_"));
+        match code {
+            Some("105" | "106" | "107" | "4018") => BindError::AuthFailed,
+            Some("334") => BindError::DomainInUse,
+            Some("313" | "319" | "320" | "326") => BindError::Forbidden,
+            Some("108" | "324" | "715") => BindError::RateLimited,
+            _ => BindError::Other(resp.msg()),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum StartSessionError {
@@ -136,11 +216,13 @@ pub enum AcceptError {
 }
 
 pub struct RpcClient {
-    // This is held so that the heartbeat task doesn't get shutdown. Eventually
-    // we may use it to request heartbeats via the `Session`.
-    #[allow(dead_code)]
-    heartbeat: HeartbeatCtl,
+    // Held so that the heartbeat task doesn't get shutdown, and to request
+    // heartbeats on demand. Shared so that callers can wait on a heartbeat
+    // without holding on to the client.
+    heartbeat: Arc<HeartbeatCtl>,
     open: Box<dyn TypedOpenClose + Send>,
+    // The limit on each RPC round-trip, if any.
+    timeout: Option<Duration>,
 }
 
 pub struct IncomingStreams {
@@ -215,8 +297,9 @@ impl RawSession {
 
         let sess = RawSession {
             client: RpcClient {
-                heartbeat: hbctl,
+                heartbeat: Arc::new(hbctl),
                 open: Box::new(open),
+                timeout: None,
             },
             incoming: IncomingStreams {
                 runtime,
@@ -239,8 +322,33 @@ impl RawSession {
 }
 
 impl RpcClient {
+    pub fn heartbeat(&self) -> Arc<HeartbeatCtl> {
+        self.heartbeat.clone()
+    }
+
+    /// Limit how long each RPC may take, from opening its stream to reading
+    /// the response, before it fails with an [io::ErrorKind::TimedOut]
+    /// [RpcError::Receive] error.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
     #[instrument(level = "debug", skip(self))]
     async fn rpc<R: RpcRequest>(&mut self, req: R) -> Result<R::Response, RpcError> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.round_trip(req))
+                .await
+                .map_err(|_| {
+                    RpcError::Receive(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("no rpc response after {timeout:?}"),
+                    ))
+                })?,
+            None => self.round_trip(req).await,
+        }
+    }
+
+    async fn round_trip<R: RpcRequest>(&mut self, req: R) -> Result<R::Response, RpcError> {
         let mut stream = self
             .open
             .open_typed(R::TYPE)
@@ -320,6 +428,7 @@ impl RpcClient {
         extra: BindExtra,
         id: impl Into<String> + Debug,
         forwards_to: impl Into<String> + Debug,
+        forwards_proto: impl Into<String> + Debug,
     ) -> Result<BindResp<BindOpts>, RpcError> {
         // Sorry, this is awful. Serde untagged unions are pretty fraught and
         // hard to debug, so we're using this macro to specialize this call
@@ -337,6 +446,7 @@ impl RpcClient {
                             client_id: id.into(),
                             proto: protocol.into(),
                             forwards_to: forwards_to.into(),
+                            forwards_proto: forwards_proto.into(),
                             opts,
                             extra,
                         };
@@ -362,11 +472,13 @@ impl RpcClient {
         labels: HashMap<String, String>,
         metadata: impl Into<String> + Debug,
         forwards_to: impl Into<String> + Debug,
+        forwards_proto: impl Into<String> + Debug,
     ) -> Result<StartTunnelWithLabelResp, RpcError> {
         let req = StartTunnelWithLabel {
             labels,
             metadata: metadata.into(),
             forwards_to: forwards_to.into(),
+            forwards_proto: forwards_proto.into(),
         };
 
         self.rpc(req).await
@@ -468,3 +580,76 @@ pub struct TunnelStream {
     pub header: ProxyHeader,
     pub stream: TypedStream,
 }
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use muxado::{
+        typed::Typed,
+        MuxadoSession,
+    };
+
+    use super::*;
+
+    // Start an RpcClient over an in-memory connection, along with the other
+    // end of it to play the part of the ngrok service.
+    pub(crate) async fn rpc_client() -> (RpcClient, Typed<MuxadoSession>) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (client, _) = RawSession::start(client, HeartbeatConfig::default(), None)
+            .await
+            .unwrap()
+            .split();
+        let server = Typed::new(SessionBuilder::new(server).server().start());
+        (client, server)
+    }
+
+    // Accept the next RPC of type `R`, skipping any other streams such as
+    // heartbeats, and read its request.
+    pub(crate) async fn accept_rpc<R: RpcRequest>(
+        server: &mut Typed<MuxadoSession>,
+    ) -> (TypedStream, serde_json::Value) {
+        let mut stream = loop {
+            let stream = server.accept_typed().await.unwrap();
+            if stream.typ() == R::TYPE {
+                break stream;
+            }
+        };
+        // The client doesn't close its side after the request, so read until
+        // it parses.
+        let mut buf = vec![];
+        loop {
+            if let Ok(req) = serde_json::from_slice(&buf) {
+                return (stream, req);
+            }
+            let mut chunk = [0; 1024];
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert!(n > 0, "rpc stream closed before the request was read");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        testing::*,
+        *,
+    };
+
+    #[tokio::test]
+    async fn test_rpc_timeout() {
+        tokio::time::pause();
+        let (mut client, mut server) = rpc_client().await;
+        client.set_timeout(Some(Duration::from_secs(30)));
+
+        // The service reads the request, but never responds.
+        let start = tokio::time::Instant::now();
+        let rpc = tokio::spawn(async move { client.unlisten("tun").await });
+        let (_stream, _) = accept_rpc::<Unbind>(&mut server).await;
+
+        let Err(RpcError::Receive(error)) = rpc.await.unwrap() else {
+            panic!("expected the rpc to time out");
+        };
+        assert_eq!(io::ErrorKind::TimedOut, error.kind());
+        assert!(start.elapsed() >= Duration::from_secs(30));
+    }
+}
@@ -28,7 +28,13 @@ pub mod config {
     pub use tcp::*;
     mod tls;
     pub use tls::*;
+    mod traffic_policy;
+    pub use traffic_policy::*;
     mod webhook_verification;
+    pub use webhook_verification::{
+        InvalidWebhookProvider,
+        WebhookProvider,
+    };
 }
 
 /// Types for working with the ngrok session.
@@ -9,6 +9,7 @@ use std::{
         },
         Arc,
     },
+    time::Duration,
 };
 
 use anyhow::{
@@ -57,12 +58,14 @@ use crate::{
         OauthOptions,
         ProxyProto,
         Scheme,
+        TlsTermination,
     },
     prelude::*,
     session::{
         SessionBuilder,
         CERT_BYTES,
     },
+    tunnel::private::Forwarding,
     Session,
 };
 
@@ -100,6 +103,52 @@ async fn tunnel() -> Result<(), Error> {
     Ok(())
 }
 
+#[cfg_attr(not(feature = "online-tests"), ignore)]
+#[test]
+async fn list_and_close_tunnels() -> Result<(), Error> {
+    let sess = setup_session().await?;
+    let tun = sess
+        .http_endpoint()
+        .metadata("Hello, world!")
+        .listen()
+        .await?;
+
+    let tunnels = sess.tunnels().await;
+    assert_eq!(1, tunnels.len());
+    assert_eq!(tun.id(), tunnels[0].id());
+    assert_eq!(tun.url(), tunnels[0].url());
+    assert_eq!("https", tunnels[0].proto());
+    assert_eq!("Hello, world!", tunnels[0].metadata());
+
+    sess.close_tunnel(tun.id()).await?;
+    assert!(sess.tunnels().await.is_empty());
+
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "online-tests"), ignore)]
+#[test]
+async fn set_forwards_to() -> Result<(), Error> {
+    let sess = setup_session().await?;
+    let mut tun = sess.http_endpoint().listen().await?;
+
+    tun.set_forwards_to("http://localhost:8080".into()).await;
+    assert_eq!("http://localhost:8080", tun.forwards_to());
+    assert_eq!(vec![tun.info()], sess.tunnels().await);
+
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "online-tests"), ignore)]
+#[test]
+async fn ping() -> Result<(), Error> {
+    let sess = setup_session().await?;
+    let (first, second) = futures::join!(sess.ping(), sess.ping());
+    assert!(first? > Duration::ZERO);
+    assert!(second? > Duration::ZERO);
+    Ok(())
+}
+
 struct TunnelGuard {
     tx: Option<oneshot::Sender<()>>,
     url: String,
@@ -181,6 +230,29 @@ async fn http() -> Result<(), Error> {
     Ok(())
 }
 
+#[cfg_attr(not(feature = "online-tests"), ignore)]
+#[test]
+async fn serve_http_direct() -> Result<(), Error> {
+    let mut tun = setup_session().await?.http_endpoint().listen().await?;
+    let url = tun.url().to_string();
+
+    tokio::spawn(async move {
+        tun.serve_http(hyper::service::service_fn(
+            |req: hyper::Request<hyper::Body>| {
+                let has_addr = req.extensions().get::<SocketAddr>().is_some();
+                future::ok::<_, std::convert::Infallible>(hyper::Response::new(hyper::Body::from(
+                    format!("remote addr: {has_addr}"),
+                )))
+            },
+        ))
+        .await
+    });
+
+    check_body(url, "remote addr: true").await?;
+
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "paid-tests"), ignore)]
 #[test]
 async fn http_compression() -> Result<(), Error> {
@@ -521,7 +593,7 @@ async fn websocket_conversion() -> Result<(), Error> {
         .connect()
         .await?
         .http_endpoint()
-        .websocket_tcp_conversion()
+        .websocket_tcp_converter()
         .listen()
         .await?;
 
@@ -594,7 +666,10 @@ async fn tls() -> Result<(), Error> {
         .connect()
         .await?
         .tls_endpoint()
-        .termination(CERT.into(), KEY.into())
+        .tls_termination(TlsTermination::Edge {
+            cert_pem: CERT.into(),
+            key_pem: KEY.into(),
+        })
         .listen()
         .await?;
 
@@ -1,6 +1,8 @@
 use std::{
     collections::HashMap,
+    fmt,
     net::SocketAddr,
+    path::PathBuf,
     pin::Pin,
     sync::Arc,
     task::{
@@ -18,6 +20,8 @@ use muxado::{
     Error as MuxadoError,
 };
 use thiserror::Error;
+#[cfg(feature = "test-util")]
+use tokio::io::DuplexStream;
 use tokio::{
     io::{
         AsyncRead,
@@ -34,11 +38,43 @@ use crate::{
         TlsTunnelBuilder,
     },
     internals::raw_session::RpcError,
-    session::ConnectError,
+    session::{
+        ConnectError,
+        TunnelInfo,
+    },
     Session,
 };
+pub use crate::{
+    internals::proto::EdgeType,
+    tunnel_ext::{
+        forward_all,
+        BackoffPolicy,
+        Backpressure,
+        ConnClosed,
+        ConnMeter,
+        ConnOpened,
+        ForwardError,
+        ForwardOptions,
+        ForwardStats,
+        HealthCheck,
+        JoinMode,
+        LbStrategy,
+        RateLimitScope,
+    },
+};
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub mod testing;
 
 /// Errors arising when accepting a [Conn] from an ngrok tunnel.
+///
+/// Errors that the session recovers from, such as a malformed connection
+/// header or a dropped connection to the ngrok service that it reconnects
+/// after, are never returned from the tunnel. So the tunnel won't yield any
+/// more connections after an error, and
+/// [TunnelExt::forward](crate::prelude::TunnelExt::forward) and friends stop
+/// on the first one.
 #[derive(Error, Debug, Clone)]
 #[non_exhaustive]
 pub enum AcceptError {
@@ -48,6 +84,11 @@ pub enum AcceptError {
     /// An error arose during reconnect
     #[error("reconnect error")]
     Reconnect(#[from] Arc<ConnectError>),
+    /// The session reconnected, but the tunnel couldn't be rebound according
+    /// to its
+    /// [TunnelReconnectPolicy](crate::session::TunnelReconnectPolicy).
+    #[error("rebind error")]
+    Rebind(#[source] Arc<RpcError>),
 }
 
 pub(crate) struct TunnelInner {
@@ -57,7 +98,10 @@ pub(crate) struct TunnelInner {
     pub(crate) labels: HashMap<String, String>,
     pub(crate) forwards_to: String,
     pub(crate) metadata: String,
+    pub(crate) forward_opts: ForwardOptions,
     pub(crate) incoming: Receiver<Result<Conn, AcceptError>>,
+    // The file the tunnel's URL was written to, removed when it's closed.
+    pub(crate) url_file: Option<PathBuf>,
 
     // Note: this session field is also used to detect tunnel liveness for the
     // purposes of shutting down the accept loop. If it's ever removed, an
@@ -68,6 +112,8 @@ pub(crate) struct TunnelInner {
 
 impl Drop for TunnelInner {
     fn drop(&mut self) {
+        self.forward_opts.tunnel_closed.cancel();
+        self.remove_url_file();
         let id = self.id().to_string();
         let sess = self.session.clone();
         let rt = sess.runtime();
@@ -85,6 +131,13 @@ macro_rules! tunnel_trait {
         /// ngrok [Tunnel]s act like TCP listeners and can be used as a
         /// [futures::stream::TryStream] of [Conn]ections from endpoints created on the ngrok
         /// service.
+        ///
+        /// A tunnel's edge configuration, such as its header modifications
+        /// or IP restrictions, is fixed once it's started, since the ngrok
+        /// protocol has no call to change it in place. It has to be started
+        /// again with the new configuration instead. For labeled tunnels,
+        /// start the new one with the same labels before closing the old
+        /// one, so the edge always has a tunnel to route to.
         #[async_trait]
         pub trait Tunnel:
             Stream<Item = Result<Conn, AcceptError>>
@@ -104,6 +157,21 @@ macro_rules! tunnel_trait {
             fn metadata(&self) -> &str;
             /// Returns the protocol for this tunnel.
             fn proto(&self) -> &str;
+            /// Returns everything known about this tunnel, including the URL
+            /// assigned to it by the ngrok service.
+            ///
+            /// The default implementation is built from the other accessors,
+            /// with an empty URL and no labels.
+            fn info(&self) -> TunnelInfo {
+                TunnelInfo {
+                    id: self.id().into(),
+                    proto: self.proto().into(),
+                    url: String::new(),
+                    labels: HashMap::new(),
+                    forwards_to: self.forwards_to().into(),
+                    metadata: self.metadata().into(),
+                }
+            }
             /// Close the tunnel.
             ///
             /// This is an RPC call that must be `.await`ed.
@@ -123,6 +191,26 @@ tunnel_trait!();
 #[cfg(feature = "hyper")]
 tunnel_trait!(+ Accept<Conn = Conn, Error = AcceptError>);
 
+pub(crate) mod private {
+    use async_trait::async_trait;
+
+    use super::ForwardOptions;
+
+    // The forwarding state behind the TunnelExt setters. Kept out of the public
+    // Tunnel trait so that only this crate's tunnels can be forwarded, and so
+    // that the options can't be changed other than through those setters.
+    #[async_trait]
+    pub trait Forwarding {
+        fn forward_options(&self) -> &ForwardOptions;
+        fn forward_options_mut(&mut self) -> &mut ForwardOptions;
+        // Replace the tunnel's forwards_to string. The ngrok service only
+        // learns of the new value when the tunnel is next bound, such as after
+        // the session reconnects. Called when forwarding, with the forwarding
+        // url, unless the tunnel was started with an explicit forwards_to.
+        async fn set_forwards_to(&mut self, forwards_to: String);
+    }
+}
+
 /// An ngrok tunnel that supports getting the URL it was started for.
 pub trait UrlTunnel: Tunnel {
     /// Returns the tunnel endpoint's URL.
@@ -144,10 +232,21 @@ pub trait LabelsTunnel: Tunnel {
 /// A connection from an ngrok tunnel.
 ///
 /// This implements [AsyncRead]/[AsyncWrite], as well as providing access to the
-/// address from which the connection to the ngrok edge originated.
+/// address from which the connection to the ngrok edge originated and other
+/// details about how it arrived.
+///
+/// It's also [Unpin] and [Send], without any dynamic dispatch, so custom
+/// forwarding loops can hold it by value in their own types and move it
+/// between tasks without boxing it first.
 pub struct Conn {
+    pub(crate) tunnel_id: String,
     pub(crate) remote_addr: SocketAddr,
-    pub(crate) stream: TypedStream,
+    pub(crate) proto: String,
+    pub(crate) http2: bool,
+    pub(crate) edge_type: EdgeType,
+    pub(crate) passthrough_tls: bool,
+    pub(crate) raw_header: Vec<u8>,
+    pub(crate) stream: ConnStream,
 }
 
 impl Stream for TunnelInner {
@@ -188,9 +287,26 @@ impl TunnelInner {
     pub async fn close(&mut self) -> Result<(), RpcError> {
         self.session.close_tunnel(&self.id).await?;
         self.incoming.close();
+        self.forward_opts.tunnel_closed.cancel();
+        self.remove_url_file();
         Ok(())
     }
 
+    fn remove_url_file(&mut self) {
+        if let Some(path) = self.url_file.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Replace the forwards_to string for this tunnel, both here and in the
+    /// session's record used to rebind it.
+    pub async fn set_forwards_to(&mut self, forwards_to: String) {
+        self.session
+            .set_forwards_to(&self.id, forwards_to.clone())
+            .await;
+        self.forwards_to = forwards_to;
+    }
+
     /// Get the protocol that this tunnel uses.
     pub fn proto(&self) -> &str {
         &self.proto
@@ -211,14 +327,117 @@ impl TunnelInner {
     pub fn metadata(&self) -> &str {
         &self.metadata
     }
+
+    /// Get a snapshot of this tunnel's information.
+    pub fn info(&self) -> TunnelInfo {
+        TunnelInfo {
+            id: self.id.clone(),
+            proto: self.proto.clone(),
+            url: self.url.clone(),
+            labels: self.labels.clone(),
+            forwards_to: self.forwards_to.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Get the options for forwarding this tunnel's connections.
+    pub fn forward_options(&self) -> &ForwardOptions {
+        &self.forward_opts
+    }
+
+    /// Get the options for forwarding this tunnel's connections for
+    /// modification.
+    pub fn forward_options_mut(&mut self) -> &mut ForwardOptions {
+        &mut self.forward_opts
+    }
 }
 
 impl Conn {
+    /// Returns the ID of the tunnel that accepted the connection.
+    pub fn tunnel_id(&self) -> &str {
+        &self.tunnel_id
+    }
+
     /// Returns the client address that initiated the connection to the ngrok
     /// edge.
     pub fn remote_addr(&self) -> SocketAddr {
         self.remote_addr
     }
+
+    /// Returns the protocol with which the client connected to the ngrok edge.
+    pub fn proto(&self) -> &str {
+        &self.proto
+    }
+
+    /// Returns whether the ngrok edge forwards HTTP/2 over this connection.
+    ///
+    /// This is only the case for HTTP endpoints when the session was
+    /// configured with
+    /// [app_protocol("http2")](crate::session::SessionBuilder::app_protocol).
+    pub fn http2(&self) -> bool {
+        self.http2
+    }
+
+    /// Returns the type of edge the connection arrived through.
+    ///
+    /// The ngrok service only sends the edge's type with each connection, not
+    /// its ID.
+    pub fn edge_type(&self) -> EdgeType {
+        self.edge_type
+    }
+
+    /// Returns whether the client's TLS connection is being passed through
+    /// the ngrok edge unterminated, in which case the handshake will be
+    /// performed over this connection.
+    pub fn passthrough_tls(&self) -> bool {
+        self.passthrough_tls
+    }
+
+    /// Returns the header the ngrok edge sent ahead of the connection's data,
+    /// exactly as it was received.
+    ///
+    /// This is a JSON object, without the length prefix it was framed with.
+    /// It's an escape hatch for fields that don't have their own accessor
+    /// yet. Its format is internal to the ngrok service and may change
+    /// without notice.
+    ///
+    /// The header doesn't include the hostname the client connected to. When
+    /// TLS is passed through, that's the server name (SNI) in the client's
+    /// ClientHello, the first thing read from this connection. For HTTP
+    /// edges, it's the request's `Host` header.
+    pub fn proxy_header_bytes(&self) -> &[u8] {
+        &self.raw_header
+    }
+}
+
+impl fmt::Display for Conn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The proto comes straight from the edge, and may be missing for
+        // connections that didn't arrive through one.
+        let proto = if self.proto.is_empty() {
+            "unknown"
+        } else {
+            &self.proto
+        };
+        write!(
+            f,
+            "{proto} connection from {} on tunnel {}",
+            self.remote_addr, self.tunnel_id
+        )
+    }
+}
+
+impl fmt::Debug for Conn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Conn")
+            .field("tunnel_id", &self.tunnel_id)
+            .field("remote_addr", &self.remote_addr)
+            .field("proto", &self.proto)
+            .field("edge_type", &self.edge_type)
+            .field("http2", &self.http2)
+            .field("passthrough_tls", &self.passthrough_tls)
+            .finish_non_exhaustive()
+    }
 }
 
 impl AsyncRead for Conn {
@@ -227,7 +446,7 @@ impl AsyncRead for Conn {
         cx: &mut Context<'_>,
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<std::io::Result<()>> {
-        Pin::new(&mut *self.stream).poll_read(cx, buf)
+        Pin::new(&mut self.stream).poll_read(cx, buf)
     }
 }
 
@@ -237,25 +456,110 @@ impl AsyncWrite for Conn {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, std::io::Error>> {
-        Pin::new(&mut *self.stream).poll_write(cx, buf)
+        Pin::new(&mut self.stream).poll_write(cx, buf)
     }
     fn poll_flush(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<(), std::io::Error>> {
-        Pin::new(&mut *self.stream).poll_flush(cx)
+        Pin::new(&mut self.stream).poll_flush(cx)
     }
     fn poll_shutdown(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<(), std::io::Error>> {
-        Pin::new(&mut *self.stream).poll_shutdown(cx)
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+// The stream underlying a [Conn].
+pub(crate) enum ConnStream {
+    Tunnel(TypedStream),
+    #[cfg(feature = "test-util")]
+    Memory(DuplexStream),
+}
+
+impl AsyncRead for ConnStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            ConnStream::Tunnel(stream) => Pin::new(&mut **stream).poll_read(cx, buf),
+            #[cfg(feature = "test-util")]
+            ConnStream::Memory(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for ConnStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        match self.get_mut() {
+            ConnStream::Tunnel(stream) => Pin::new(&mut **stream).poll_write(cx, buf),
+            #[cfg(feature = "test-util")]
+            ConnStream::Memory(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        match self.get_mut() {
+            ConnStream::Tunnel(stream) => Pin::new(&mut **stream).poll_flush(cx),
+            #[cfg(feature = "test-util")]
+            ConnStream::Memory(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        match self.get_mut() {
+            ConnStream::Tunnel(stream) => Pin::new(&mut **stream).poll_shutdown(cx),
+            #[cfg(feature = "test-util")]
+            ConnStream::Memory(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
     }
 }
 
 // Support for axum's connection info trait.
 #[cfg(feature = "axum")]
 use axum::extract::connect_info::Connected;
+/// Makes a tunnel connection's [Conn::remote_addr] available to axum handlers
+/// via [ConnectInfo](axum::extract::ConnectInfo).
+///
+/// Since tunnels implement hyper's [Accept] trait, they can be passed to
+/// [axum::Server::builder] directly in place of a bound listener:
+///
+/// ```no_run
+/// # use std::net::SocketAddr;
+/// # use axum::{extract::ConnectInfo, routing::get, Router};
+/// use ngrok::prelude::*;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let app = Router::new().route(
+///     "/",
+///     get(|ConnectInfo(addr): ConnectInfo<SocketAddr>| async move {
+///         format!("Hello, {addr}!")
+///     }),
+/// );
+///
+/// let tun = ngrok::Session::builder()
+///     .authtoken_from_env()
+///     .connect()
+///     .await?
+///     .http_endpoint()
+///     .listen()
+///     .await?;
+///
+/// axum::Server::builder(tun)
+///     .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
 #[cfg_attr(docsrs, doc(cfg(feature = "axum")))]
 #[cfg(feature = "axum")]
 impl Connected<&Conn> for SocketAddr {
@@ -264,6 +568,93 @@ impl Connected<&Conn> for SocketAddr {
     }
 }
 
+// Support for tonic's connection info trait.
+#[cfg(feature = "tonic")]
+use tonic::transport::server::Connected as TonicConnected;
+
+/// Information about the tunnel connection a gRPC request arrived on.
+///
+/// With the `tonic` feature, it's available to handlers via
+/// `request.extensions().get::<TonicConnectInfo>()` when serving on
+/// [incoming](HttpTunnel::incoming) tunnel connections with tonic's
+/// `Server::serve_with_incoming`.
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+#[cfg(feature = "tonic")]
+#[derive(Debug, Clone)]
+pub struct TonicConnectInfo {
+    remote_addr: SocketAddr,
+    proto: String,
+    http2: bool,
+    edge_type: EdgeType,
+}
+
+#[cfg(feature = "tonic")]
+impl TonicConnectInfo {
+    /// Returns the client address that initiated the connection to the ngrok
+    /// edge.
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// Returns the protocol with which the client connected to the ngrok edge.
+    pub fn proto(&self) -> &str {
+        &self.proto
+    }
+
+    /// Returns whether the ngrok edge forwards HTTP/2 over the connection.
+    pub fn http2(&self) -> bool {
+        self.http2
+    }
+
+    /// Returns the type of edge the connection arrived through.
+    pub fn edge_type(&self) -> EdgeType {
+        self.edge_type
+    }
+}
+
+/// Lets tonic serve gRPC on tunnel connections.
+///
+/// gRPC requires HTTP/2 all the way to the server. TCP and TLS tunnels pass
+/// the client's connection through as-is, while HTTP tunnels only forward
+/// HTTP/2 if the session is configured with
+/// [app_protocol("http2")](crate::session::SessionBuilder::app_protocol).
+///
+/// ```no_run
+/// # use tonic::transport::{server::Routes, Server};
+/// use ngrok::prelude::*;
+///
+/// # async fn run(routes: Routes) -> anyhow::Result<()> {
+/// let tun = ngrok::Session::builder()
+///     .authtoken_from_env()
+///     .app_protocol("http2")
+///     .connect()
+///     .await?
+///     .http_endpoint()
+///     .listen()
+///     .await?;
+///
+/// Server::builder()
+///     .add_routes(routes)
+///     .serve_with_incoming(tun.incoming())
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+#[cfg(feature = "tonic")]
+impl TonicConnected for Conn {
+    type ConnectInfo = TonicConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        TonicConnectInfo {
+            remote_addr: self.remote_addr,
+            proto: self.proto.clone(),
+            http2: self.http2,
+            edge_type: self.edge_type,
+        }
+    }
+}
+
 macro_rules! make_tunnel_type {
     ($(#[$outer:meta])* $wrapper:ident, $builder:tt, $($m:tt),*) => {
         $(#[$outer])*
@@ -292,6 +683,25 @@ macro_rules! make_tunnel_type {
             fn proto(&self) -> &str {
                 self.inner.proto()
             }
+
+            fn info(&self) -> TunnelInfo {
+                self.inner.info()
+            }
+        }
+
+        #[async_trait]
+        impl private::Forwarding for $wrapper {
+            fn forward_options(&self) -> &ForwardOptions {
+                self.inner.forward_options()
+            }
+
+            fn forward_options_mut(&mut self) -> &mut ForwardOptions {
+                self.inner.forward_options_mut()
+            }
+
+            async fn set_forwards_to(&mut self, forwards_to: String) {
+                self.inner.set_forwards_to(forwards_to).await
+            }
         }
 
         impl $wrapper {
@@ -299,6 +709,21 @@ macro_rules! make_tunnel_type {
             pub fn builder(session: Session) -> $builder {
                 $builder::from(session)
             }
+
+            /// Convert this tunnel into a [Stream] of the [Conn]ections it
+            /// accepts.
+            ///
+            /// This is useful for handling connections with stream combinators
+            /// such as [futures::StreamExt::for_each_concurrent] rather than
+            /// via [TunnelExt::forward](crate::prelude::TunnelExt::forward).
+            ///
+            /// The stream takes ownership of the tunnel, so dropping it will
+            /// close the tunnel.
+            pub fn incoming(
+                self,
+            ) -> impl Stream<Item = Result<Conn, AcceptError>> + Unpin + Send + 'static {
+                self
+            }
         }
 
         $(
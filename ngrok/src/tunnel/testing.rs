@@ -0,0 +1,457 @@
+//! In-memory tunnels for testing code that handles tunnel connections without
+//! connecting to the ngrok service.
+//!
+//! A [MockTunnel] behaves like any other [Tunnel], including when forwarding
+//! with [TunnelExt](crate::prelude::TunnelExt), but its connections are opened
+//! by hand through the [MockEdge] created alongside it:
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! use ngrok::{
+//!     prelude::*,
+//!     tunnel::testing::MockTunnel,
+//! };
+//! use tokio::{
+//!     io::{
+//!         AsyncReadExt,
+//!         AsyncWriteExt,
+//!     },
+//!     net::TcpListener,
+//! };
+//!
+//! let listener = TcpListener::bind("127.0.0.1:0").await?;
+//! let url = format!("tcp://{}", listener.local_addr()?).parse()?;
+//!
+//! let (mut tunnel, edge) = MockTunnel::new("tcp");
+//! tokio::spawn(async move { tunnel.forward(url).await });
+//!
+//! let mut client = edge.connect("1.2.3.4:5678".parse()?);
+//! client.write_all(b"hello").await?;
+//!
+//! let (mut local, _) = listener.accept().await?;
+//! let mut buf = [0; 5];
+//! local.read_exact(&mut buf).await?;
+//! assert_eq!(b"hello", &buf);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    pin::Pin,
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    task::{
+        Context,
+        Poll,
+    },
+};
+
+use async_trait::async_trait;
+use futures::Stream;
+#[cfg(feature = "hyper")]
+use hyper::server::accept::Accept;
+use tokio::{
+    io::{
+        self,
+        DuplexStream,
+    },
+    sync::mpsc,
+};
+
+use super::{
+    private::Forwarding,
+    AcceptError,
+    Conn,
+    ConnStream,
+    ProtoTunnel,
+    Tunnel,
+    UrlTunnel,
+};
+use crate::{
+    config::default_forwards_to,
+    internals::proto::EdgeType,
+    session::{
+        RpcError,
+        TunnelInfo,
+    },
+    tunnel_ext::ForwardOptions,
+};
+
+// The size of the buffers between each end of a mock connection.
+const DUPLEX_SIZE: usize = 64 * 1024;
+
+/// A [Tunnel] whose connections are opened in memory by its [MockEdge].
+pub struct MockTunnel {
+    id: String,
+    proto: String,
+    url: String,
+    forwards_to: String,
+    metadata: String,
+    forward_opts: ForwardOptions,
+    incoming: mpsc::UnboundedReceiver<Conn>,
+}
+
+/// Opens connections to a [MockTunnel], as the ngrok edge would.
+///
+/// Dropping the edge ends the tunnel's stream of connections once those
+/// already opened have been accepted.
+pub struct MockEdge {
+    tunnel_id: String,
+    proto: String,
+    tx: mpsc::UnboundedSender<Conn>,
+}
+
+impl MockTunnel {
+    /// Create a tunnel with the given protocol, such as `"http"` or `"tcp"`,
+    /// along with the edge that opens its connections.
+    ///
+    /// The protocol determines how failures to connect to the local service
+    /// are reported when forwarding, as it does for real tunnels.
+    pub fn new(proto: impl Into<String>) -> (MockTunnel, MockEdge) {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let id = format!("tn_mock_{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let proto = proto.into();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let tunnel = MockTunnel {
+            id: id.clone(),
+            url: format!("{proto}://{id}.ngrok.invalid"),
+            proto: proto.clone(),
+            forwards_to: default_forwards_to().into(),
+            metadata: Default::default(),
+            forward_opts: Default::default(),
+            incoming: rx,
+        };
+        let edge = MockEdge {
+            tunnel_id: id,
+            proto,
+            tx,
+        };
+        (tunnel, edge)
+    }
+}
+
+impl MockEdge {
+    /// Open a connection to the tunnel from a client at the given address,
+    /// returning the client's end of it.
+    ///
+    /// If the tunnel has been closed or dropped, the returned stream is
+    /// already closed by the other side.
+    pub fn connect(&self, remote_addr: SocketAddr) -> DuplexStream {
+        let (client, server) = io::duplex(DUPLEX_SIZE);
+        let _ = self.tx.send(Conn {
+            tunnel_id: self.tunnel_id.clone(),
+            remote_addr,
+            proto: self.proto.clone(),
+            http2: false,
+            edge_type: EdgeType::Undefined,
+            passthrough_tls: false,
+            raw_header: Default::default(),
+            stream: ConnStream::Memory(server),
+        });
+        client
+    }
+}
+
+impl Drop for MockTunnel {
+    fn drop(&mut self) {
+        self.forward_opts.tunnel_closed.cancel();
+    }
+}
+
+#[async_trait]
+impl Tunnel for MockTunnel {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn close(&mut self) -> Result<(), RpcError> {
+        self.incoming.close();
+        self.forward_opts.tunnel_closed.cancel();
+        Ok(())
+    }
+
+    fn forwards_to(&self) -> &str {
+        &self.forwards_to
+    }
+
+    fn metadata(&self) -> &str {
+        &self.metadata
+    }
+
+    fn proto(&self) -> &str {
+        &self.proto
+    }
+
+    fn info(&self) -> TunnelInfo {
+        TunnelInfo {
+            id: self.id.clone(),
+            proto: self.proto.clone(),
+            url: self.url.clone(),
+            labels: HashMap::new(),
+            forwards_to: self.forwards_to.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Forwarding for MockTunnel {
+    fn forward_options(&self) -> &ForwardOptions {
+        &self.forward_opts
+    }
+
+    fn forward_options_mut(&mut self) -> &mut ForwardOptions {
+        &mut self.forward_opts
+    }
+
+    async fn set_forwards_to(&mut self, forwards_to: String) {
+        self.forwards_to = forwards_to;
+    }
+}
+
+impl UrlTunnel for MockTunnel {
+    fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl ProtoTunnel for MockTunnel {
+    fn proto(&self) -> &str {
+        &self.proto
+    }
+}
+
+impl Stream for MockTunnel {
+    type Item = Result<Conn, AcceptError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.incoming.poll_recv(cx).map(|conn| conn.map(Ok))
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl Accept for MockTunnel {
+    type Conn = Conn;
+    type Error = AcceptError;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        self.poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::TryStreamExt;
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    };
+
+    use super::*;
+    use crate::{
+        prelude::*,
+        tunnel::{
+            forward_all,
+            JoinMode,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_mock_tunnel() {
+        let (mut tunnel, edge) = MockTunnel::new("tcp");
+        let mut client = edge.connect("1.2.3.4:5678".parse().unwrap());
+        drop(edge);
+
+        let mut conn = tunnel.try_next().await.unwrap().unwrap();
+        assert_eq!(tunnel.id(), conn.tunnel_id());
+        assert_eq!("1.2.3.4:5678", conn.remote_addr().to_string());
+        assert_eq!("tcp", conn.proto());
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        conn.write_all(b"pong").await.unwrap();
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"pong", &buf);
+
+        // Dropping the edge ends the tunnel.
+        assert!(tunnel.try_next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_conn_fmt() {
+        let (mut tunnel, edge) = MockTunnel::new("tcp");
+        let _client = edge.connect("1.2.3.4:5678".parse().unwrap());
+        let conn = tunnel.try_next().await.unwrap().unwrap();
+        assert_eq!(
+            format!("tcp connection from 1.2.3.4:5678 on tunnel {}", tunnel.id()),
+            conn.to_string()
+        );
+        assert_eq!(
+            format!(
+                "Conn {{ tunnel_id: {:?}, remote_addr: 1.2.3.4:5678, proto: \"tcp\", \
+                 edge_type: Undefined, http2: false, passthrough_tls: false, .. }}",
+                tunnel.id()
+            ),
+            format!("{conn:?}")
+        );
+
+        // Connections without a proto still say what they are.
+        let (mut tunnel, edge) = MockTunnel::new("");
+        let _client = edge.connect("1.2.3.4:5678".parse().unwrap());
+        let conn = tunnel.try_next().await.unwrap().unwrap();
+        assert!(conn.to_string().starts_with("unknown connection from"));
+    }
+
+    #[tokio::test]
+    async fn test_forward_tcp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let (mut tunnel, edge) = MockTunnel::new("tcp");
+        let forward = tokio::spawn(async move { tunnel.forward(url).await });
+
+        let mut client = edge.connect("1.2.3.4:5678".parse().unwrap());
+        let (mut local, _) = listener.accept().await.unwrap();
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        local.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"hello", &buf);
+
+        drop(edge);
+        forward.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_forward_router() {
+        let internal = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let public = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let internal_url: url::Url = format!("tcp://{}", internal.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let public_url: url::Url = format!("tcp://{}", public.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let (mut tunnel, edge) = MockTunnel::new("tcp");
+        let forward = tokio::spawn(async move {
+            tunnel
+                .forward_router(move |conn| {
+                    if conn.remote_addr().ip().is_loopback() {
+                        internal_url.clone()
+                    } else {
+                        public_url.clone()
+                    }
+                })
+                .await
+        });
+
+        for (remote_addr, listener) in [("127.0.0.1:5678", &internal), ("1.2.3.4:5678", &public)] {
+            let mut client = edge.connect(remote_addr.parse().unwrap());
+            let (mut local, _) = listener.accept().await.unwrap();
+            client.write_all(b"hello").await.unwrap();
+            let mut buf = [0; 5];
+            local.read_exact(&mut buf).await.unwrap();
+            assert_eq!(b"hello", &buf);
+        }
+
+        drop(edge);
+        forward.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_forward_all() {
+        let mut listeners = vec![];
+        let mut tunnels = vec![];
+        let mut edges = vec![];
+        for _ in 0..2 {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("tcp://{}", listener.local_addr().unwrap())
+                .parse()
+                .unwrap();
+            let (tunnel, edge) = MockTunnel::new("tcp");
+            listeners.push(listener);
+            tunnels.push((tunnel, url));
+            edges.push(edge);
+        }
+        let forward = tokio::spawn(forward_all(tunnels, JoinMode::All));
+
+        // Each tunnel is forwarded to its own url.
+        for (edge, listener) in edges.iter().zip(&listeners) {
+            let mut client = edge.connect("1.2.3.4:5678".parse().unwrap());
+            let (mut local, _) = listener.accept().await.unwrap();
+            client.write_all(b"hello").await.unwrap();
+            let mut buf = [0; 5];
+            local.read_exact(&mut buf).await.unwrap();
+            assert_eq!(b"hello", &buf);
+        }
+
+        // It returns once every tunnel has closed.
+        drop(edges.pop());
+        tokio::task::yield_now().await;
+        assert!(!forward.is_finished());
+        drop(edges);
+        forward.await.unwrap().unwrap();
+    }
+
+    #[cfg(feature = "hyper")]
+    #[tokio::test]
+    async fn test_forward_h2c_gateway_error() {
+        // Nothing listens on the local port, and the edge speaks HTTP/1
+        // regardless of the backend's scheme.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("h2c://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        drop(listener);
+
+        let (mut tunnel, edge) = MockTunnel::new("http");
+        let forward = tokio::spawn(async move { tunnel.forward(url).await });
+
+        let mut client = edge.connect("1.2.3.4:5678".parse().unwrap());
+        client
+            .write_all(b"GET / HTTP/1.1\r\nhost: example.com\r\n\r\n")
+            .await
+            .unwrap();
+        let mut resp = String::new();
+        client.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 502 Bad Gateway\r\n"), "{resp}");
+
+        drop(edge);
+        forward.await.unwrap().unwrap();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn test_forward_unix() {
+        let path =
+            std::env::temp_dir().join(format!("ngrok-test-mock-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let url = format!("unix:{}", path.display()).parse().unwrap();
+
+        let (mut tunnel, edge) = MockTunnel::new("tcp");
+        let forward = tokio::spawn(async move { tunnel.forward(url).await });
+
+        let mut client = edge.connect("1.2.3.4:5678".parse().unwrap());
+        let (mut local, _) = listener.accept().await.unwrap();
+        local.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"hello", &buf);
+
+        drop(edge);
+        forward.await.unwrap().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+}